@@ -1,75 +1,244 @@
+mod add;
+mod backups;
+mod balance;
+mod check;
+mod day;
 mod dump;
 mod edit;
 mod enter;
 mod exit;
+mod export;
+mod idle;
+mod import;
+mod invoice;
+mod pause;
+mod report;
+mod restore;
+mod session;
 mod stats;
 mod status;
 mod undo;
 
-use std::fs;
+use std::process::ExitCode;
 
-use chrono::{Duration, NaiveTime, Utc};
+use chrono::{DateTime, FixedOffset, Utc};
 
 use crate::{
     app::{
-        cli::{Action, Day},
+        cli::{Action, BackupsAction, CalendarOutput, Day, DayOutput, ExportFormat, GroupBy},
         context::Context,
     },
     error::{self, Result},
-    record::{self, Record},
+    record::{Entry, Labels, Record, Storage},
     script_hook,
-    time::ContextTimeZone,
+    string::assert_no_newlines,
+    time::{ContextTimeZone, NaiveDateOperations},
 };
 
-pub fn run<Tz: ContextTimeZone>(
+/// Runs the hook called `name`, unless hooks are skipped
+fn hook<Tz: ContextTimeZone>(
     ctx: &Context<Tz>,
-    action: &Action,
-    mut record: Record<Utc>,
+    name: &str,
+    payload: &script_hook::Payload,
 ) -> Result<()> {
+    if !ctx.skip_hooks {
+        script_hook::run(name, payload, &ctx.config.hooks)?;
+    }
+    Ok(())
+}
+
+/// Runs the hooks called `names` for a punch that's already saved.
+///
+/// Every one of them runs even if an earlier one fails, then the first failure is returned.
+fn hooks_after_save<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    names: &[&str],
+    payload: &script_hook::Payload,
+) -> Result<()> {
+    let mut first_error = None;
+    for name in names {
+        if let Err(err) = hook(ctx, name, payload) {
+            first_error.get_or_insert(err);
+        }
+    }
+    first_error.map_or(Ok(()), Err)
+}
+
+/// Whether a punch `at` is the first on a later day than the last one in `record`
+fn is_day_rollover<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    record: &Record<Utc>,
+    at: DateTime<FixedOffset>,
+) -> Result<bool> {
+    let day = |datetime: DateTime<Utc>| datetime.with_timezone(&ctx.timezone).date_naive();
+    Ok(record
+        .clone_last_datetime()?
+        .is_some_and(|last| day(last) < day(at.with_timezone(&Utc))))
+}
+
+/// Runs `action`, returning the code to exit with
+pub fn run<Tz: ContextTimeZone>(ctx: &Context<Tz>, action: &Action) -> Result<ExitCode> {
+    // `check` reads the record itself, so it can explain why it fails to load
+    if let Action::Check = action {
+        return check::run(ctx).map(|()| ExitCode::SUCCESS);
+    }
+
+    // `status` is kept quick for shell prompts, so it only reads the end of the record
+    if let Action::Status { format } = action {
+        let Some(latest) = Storage::load_latest()? else {
+            return Err(error::Main::Uninitialized);
+        };
+        return status::run(ctx, &latest, format);
+    }
+
+    let Some((mut storage, mut record)) = Storage::load(ctx.config.backups.keep)? else {
+        return Err(error::Main::Uninitialized);
+    };
+
+    // Commands that end or rewrite the current session deal with it themselves
+    let handles_current_session = matches!(
+        action,
+        Action::Out { .. }
+            | Action::Resume { .. }
+            | Action::Edit { .. }
+            | Action::Undo
+            | Action::Session { .. }
+            | Action::Restore { .. }
+            | Action::Ping
+    );
+    // Read-only commands leave it be, so their output stays fit for scripts
+    let read_only = matches!(
+        action,
+        Action::Dump
+            | Action::Stats { .. }
+            | Action::Calendar { .. }
+            | Action::Report { .. }
+            | Action::Balance { .. }
+            | Action::Invoice { .. }
+            | Action::Export { .. }
+            | Action::Backups { .. }
+    );
+    if !handles_current_session && !read_only && idle::run(ctx, &mut record)? {
+        storage.save(&record)?;
+    }
+
     match action {
-        Action::In { comment } => {
-            if !ctx.skip_hooks {
-                script_hook::run("before-in")?;
-            }
+        Action::In {
+            comment,
+            project,
+            tags,
+            when,
+        } => {
+            let labels = Labels::try_new(project.clone(), tags.clone())?;
+            let at = when.resolve(ctx)?;
+            let payload =
+                script_hook::Payload::clock_in(at.fixed_offset(), comment.clone(), &labels);
+            let rolled_over = is_day_rollover(ctx, &record, at.fixed_offset())?;
 
-            enter::run(&mut record, comment.clone())?;
+            hook(ctx, "before-in", &payload)?;
 
-            fs::write(".punch_clock/record", record.serialize()?)?;
+            enter::run(&mut record, comment.clone(), labels, at)?;
 
-            if !ctx.skip_hooks {
-                script_hook::run("in")?;
+            storage.save(&record)?;
+
+            let mut hooks = vec!["in"];
+            if rolled_over {
+                hooks.push("day-rollover");
             }
+            hooks_after_save(ctx, &hooks, &payload)?;
         }
-        Action::Out { comment } => {
-            if !ctx.skip_hooks {
-                script_hook::run("before-out")?;
-            }
+        Action::Out { comment, when } => {
+            let at = when.resolve(ctx)?;
+            let payload = script_hook::Payload::clock_out(
+                at.fixed_offset(),
+                comment.clone(),
+                record.get_current_session(),
+            );
+            let rolled_over = is_day_rollover(ctx, &record, at.fixed_offset())?;
+
+            hook(ctx, "before-out", &payload)?;
 
-            exit::run(&mut record, comment.clone())?;
+            exit::run(&mut record, comment.clone(), at)?;
 
-            fs::write(".punch_clock/record", record.serialize()?)?;
+            storage.save(&record)?;
 
-            if !ctx.skip_hooks {
-                script_hook::run("out")?;
+            let mut hooks = vec!["out"];
+            if rolled_over {
+                hooks.push("day-rollover");
             }
+            let long_session = ctx.config.hooks.long_session.num_seconds();
+            if payload
+                .session_duration_seconds
+                .is_some_and(|seconds| seconds > long_session)
+            {
+                hooks.push("long-session");
+            }
+            hooks_after_save(ctx, &hooks, &payload)?;
+        }
+        Action::Break { comment, when } => {
+            let at = when.resolve(ctx)?;
+            let payload = script_hook::Payload::at(at.fixed_offset(), comment.clone());
+
+            pause::run(&mut record, comment.clone(), at)?;
+
+            storage.save(&record)?;
+
+            hook(ctx, "break", &payload)?;
+        }
+        Action::Resume { when } => {
+            let at = when.resolve(ctx)?;
+
+            pause::resume(&mut record, at)?;
+
+            storage.save(&record)?;
+
+            hook(
+                ctx,
+                "resume",
+                &script_hook::Payload::at(at.fixed_offset(), None),
+            )?;
         }
-        Action::Status => status::run(&record)?,
+        Action::Status { .. } => unreachable!("handled before loading the record"),
+        Action::Ping => idle::ping()?,
         Action::Dump => {
-            dump::run(&record.clone().with_timezone(&ctx.timezone))?;
+            dump::run(ctx, &record.clone().with_timezone(&ctx.timezone))?;
         }
-        Action::Edit => {
-            let record = edit::run(ctx, record)?;
-            fs::write(".punch_clock/record", record.serialize()?)?;
+        Action::Edit { from, to } => {
+            let range = (from.is_some() || to.is_some()).then(|| {
+                let day =
+                    |entry: &Entry<Utc>| entry.check_in.with_timezone(&ctx.timezone).date_naive();
+                let entries = record.get_entries();
+                let start = from.as_ref().map_or(0, |Day(from)| {
+                    entries.partition_point(|entry| day(entry) < *from)
+                });
+                let end = to.as_ref().map_or(entries.len(), |Day(to)| {
+                    entries.partition_point(|entry| day(entry) <= *to)
+                });
+                start..end.max(start)
+            });
+            if let Some(edited) = edit::run(ctx, &record, range)? {
+                storage.save(&edited)?;
+                let now = ctx.timezone.now().fixed_offset();
+                hook(ctx, "edit", &script_hook::Payload::at(now, None))?;
+            }
         }
-        Action::Stats { day } => {
+        Action::Stats { day, filter } => {
             let date = day.as_ref().map(|Day(date)| *date);
+            let record = record.filtered(&filter.to_filter());
             stats::run(ctx, record.with_timezone(&ctx.timezone), date)?;
         }
-        Action::Calendar { from, to, width } => {
+        Action::Calendar {
+            from,
+            to,
+            width,
+            as_recorded,
+            output,
+            filter,
+        } => {
             let (from, to) = match (from.as_ref(), to.as_ref()) {
                 (None, Some(_)) => unreachable!(),
                 (None, None) => {
-                    let to = chrono::Local::now().date_naive();
+                    let to = ctx.timezone.now().date_naive();
                     let from = to
                         .checked_sub_days(chrono::Days::new(6))
                         .ok_or(error::Main::DateOutOfRange)?;
@@ -84,51 +253,223 @@ pub fn run<Tz: ContextTimeZone>(
                 }
                 (Some(from), Some(to)) => (from.0, to.0),
             };
-            record
-                .with_timezone(&ctx.timezone)
-                .paint_calendar(ctx, from..=to, *width)?;
+            let record = record.filtered(&filter.to_filter());
+            let width = width.unwrap_or(ctx.config.calendar.width);
+            if *as_recorded {
+                let offset_changes = record.offset_changes()?;
+                let ctx = ctx.with_timezone(*ctx.timezone.now().fixed_offset().offset());
+                let record = record.shifted_to_recorded(&ctx.timezone)?;
+                match output {
+                    CalendarOutput::Terminal => {
+                        record.paint_calendar(&ctx, from..=to, width, &offset_changes)?;
+                    }
+                    CalendarOutput::Html => {
+                        print!(
+                            "{}",
+                            record.calendar_html(&ctx, from..=to, &offset_changes)?
+                        );
+                    }
+                }
+            } else {
+                let record = record.with_timezone(&ctx.timezone);
+                match output {
+                    CalendarOutput::Terminal => {
+                        record.paint_calendar(ctx, from..=to, width, &[])?
+                    }
+                    CalendarOutput::Html => {
+                        print!("{}", record.calendar_html(ctx, from..=to, &[])?)
+                    }
+                }
+            }
         }
-        Action::Undo => {
-            undo::run(&mut record)?;
-            fs::write(".punch_clock/record", record.serialize()?)?;
+        Action::Report {
+            week,
+            month,
+            from,
+            to,
+            by,
+            filter,
+        } => {
+            let (range, default_group_by) = match (week, month, from, to) {
+                (Some(week), _, _, _) => (week.0..=week.last_day(), GroupBy::Day),
+                (_, Some(month), _, _) => (
+                    month.0..=month.last_day().ok_or(error::Main::DateOutOfRange)?,
+                    GroupBy::Week,
+                ),
+                (_, _, Some(from), Some(to)) => (from.0..=to.0, GroupBy::Day),
+                _ => {
+                    let today = ctx.timezone.now().date_naive();
+                    let monday = today.week(chrono::Weekday::Mon).first_day();
+                    (monday..=today, GroupBy::Day)
+                }
+            };
+            let record = record
+                .filtered(&filter.to_filter())
+                .with_timezone(&ctx.timezone);
+            report::run(ctx, &record, range, by.unwrap_or(default_group_by))?;
         }
-        Action::Day { date, resolution } => {
-            let record = record.with_timezone(&ctx.timezone);
-            let date = date.as_ref().map_or_else(
-                || {
-                    ctx.timezone
-                        .now()
-                        .date_naive()
-                        .and_time(NaiveTime::default())
-                        .and_local_timezone(ctx.timezone)
-                        .unwrap() // *shudder* I think I can safely assume this won't fail
-                },
-                |d| {
-                    d.0.and_time(NaiveTime::default())
-                        .and_local_timezone(ctx.timezone)
-                        .unwrap()
-                },
-            );
-            let next_date = date + Duration::days(1);
-            let total_datetime_ranges = record
-                .clone()
-                .try_into_cropped_datetime_ranges(ctx, date, next_date)?;
-            let total_duration: chrono::Duration = total_datetime_ranges.into_iter().sum();
-            println!(
-                "Total time: {} hours, {} minutes",
-                total_duration.num_hours(),
-                total_duration.num_minutes() % 60
-            );
+        Action::Balance { from, to, filter } => {
+            let record = record
+                .filtered(&filter.to_filter())
+                .with_timezone(&ctx.timezone);
+            let to = to
+                .as_ref()
+                .map_or_else(|| ctx.timezone.now().date_naive(), |Day(date)| *date);
+            let from = from
+                .as_ref()
+                .map(|Day(date)| *date)
+                .or(ctx.config.schedule.start)
+                .or_else(|| {
+                    record
+                        .clone()
+                        .into_iter()
+                        .next()
+                        .map(|item| item.check_in().date_naive())
+                })
+                .unwrap_or(to);
+            balance::run(ctx, &record, from..=to)?;
+        }
+        Action::Invoice {
+            from,
+            to,
+            rate,
+            currency,
+            round,
+            round_per,
+            format,
+            filter,
+        } => {
+            let record = record
+                .filtered(&filter.to_filter())
+                .with_timezone(&ctx.timezone);
+            let config = &ctx.config.invoice;
+            let rate = rate.or(config.rate).ok_or(error::Main::MissingRate)?;
+            let currency = currency.clone().or_else(|| config.currency.clone());
+            let rounding = round
+                .or(config.round)
+                .map(|round| (round, round_per.unwrap_or(config.round_per)));
+            let invoice = invoice::build(ctx, &record, from.0..=to.0, rate, currency, rounding)?;
+            print!("{}", invoice.render(*format)?);
+        }
+        Action::Export {
+            format,
+            from,
+            to,
+            filter,
+        } => {
+            let record = record
+                .filtered(&filter.to_filter())
+                .with_timezone(&ctx.timezone);
+            let start = from
+                .as_ref()
+                .map(|Day(date)| date.into_day_start(ctx))
+                .transpose()?;
+            let end = to
+                .as_ref()
+                .map(|Day(date)| date.into_day_end(ctx))
+                .transpose()?;
+            if let ExportFormat::Ics = format {
+                export::ics::write(
+                    record,
+                    ctx.timezone.now(),
+                    start,
+                    end,
+                    std::io::stdout().lock(),
+                )?;
+            } else {
+                let rows = export::rows(record, ctx.timezone.now(), start, end)?;
+                export::write(&rows, *format, std::io::stdout().lock())?;
+            }
+        }
+        Action::Import {
+            format,
+            file,
+            dry_run,
+        } => {
+            import::run(ctx, &mut record, *format, file, *dry_run)?;
+            if !dry_run {
+                storage.save(&record)?;
+            }
+        }
+        Action::Add {
+            start,
+            end,
+            in_comment,
+            out_comment,
+            project,
+            tags,
+            force,
+        } => {
+            let labels = Labels::try_new(project.clone(), tags.clone())?;
+            let in_comment = in_comment.clone().map(assert_no_newlines).transpose()?;
+            let out_comment = out_comment.clone().map(assert_no_newlines).transpose()?;
+
+            let start = start.resolve(ctx)?;
+            let end = end.resolve_on(ctx, start.date_naive())?;
+            let entry = Entry::try_new(start, end, in_comment, out_comment)?
+                .with_labels(labels)
+                .with_timezone(&Utc);
+
+            let payload = script_hook::Payload::entry(&entry)?;
+            add::run(&mut record, entry, *force, &ctx.timezone)?;
+            storage.save(&record)?;
+            hook(ctx, "add", &payload)?;
 
-            let tr = record::display::time_range::time_range(
+            let record = record.with_timezone(&ctx.timezone);
+            day::run(
+                ctx,
                 &record,
-                ctx.timezone.now(),
-                date..=next_date,
-                24 * resolution.as_hour_fraction(),
+                start.date_naive(),
+                &ctx.config.day.resolution,
+                DayOutput::Terminal,
+                &[],
             )?;
-            println!("{}", tr.print(6, "%R")?);
+        }
+        Action::Session { action } => {
+            if session::run(ctx, &mut record, action)? {
+                storage.save(&record)?;
+            }
+        }
+        Action::Check => unreachable!("handled before loading the record"),
+        Action::Backups {
+            action: BackupsAction::List,
+        } => backups::list(ctx, &storage)?,
+        Action::Restore { id } => {
+            if let Some(backup) = restore::run(ctx, &storage, &record, id)? {
+                storage.save(&backup)?;
+                println!("Restored the record from {id}");
+            }
+        }
+        Action::Undo => {
+            if let Some((removed, comment)) = undo::run(ctx, &mut record)? {
+                storage.save(&record)?;
+                let removed = removed.with_timezone(&ctx.timezone).fixed_offset();
+                hook(ctx, "undo", &script_hook::Payload::at(removed, comment))?;
+            }
+        }
+        Action::Day {
+            date,
+            resolution,
+            as_recorded,
+            output,
+            filter,
+        } => {
+            let record = record.filtered(&filter.to_filter());
+            let date = date
+                .as_ref()
+                .map_or_else(|| ctx.timezone.now().date_naive(), |Day(date)| *date);
+            let resolution = resolution.as_ref().unwrap_or(&ctx.config.day.resolution);
+            if *as_recorded {
+                let offset_changes = record.offset_changes()?;
+                let ctx = ctx.with_timezone(*ctx.timezone.now().fixed_offset().offset());
+                let record = record.shifted_to_recorded(&ctx.timezone)?;
+                day::run(&ctx, &record, date, resolution, *output, &offset_changes)?;
+            } else {
+                let record = record.with_timezone(&ctx.timezone);
+                day::run(ctx, &record, date, resolution, *output, &[])?;
+            }
         }
     };
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
@@ -0,0 +1,90 @@
+use chrono::Utc;
+
+use crate::{
+    error::{self, Result},
+    record::{Entry, Record},
+    time::{local_display, ContextTimeZone},
+};
+
+/// Inserts `entry` in chronological order, refusing to overlap other sessions unless `force` is set
+pub fn run<Tz: ContextTimeZone>(
+    record: &mut Record<Utc>,
+    entry: Entry<Utc>,
+    force: bool,
+    timezone: &Tz,
+) -> Result<()> {
+    let check_out = entry.get_check_out()?;
+    if check_out > Utc::now() {
+        return Err(error::Main::TimeInFuture(local_display(
+            &check_out, timezone,
+        )));
+    }
+
+    if !force {
+        if let Some(existing) = record.find_overlap(&entry)? {
+            return Err(error::Main::OverlapsSession(local_display(
+                existing.check_in(),
+                timezone,
+            )));
+        }
+    }
+
+    println!(
+        "Adding a session from {} to {}",
+        local_display(&entry.check_in, timezone),
+        local_display(&check_out, timezone)
+    );
+
+    record.insert(entry);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{FixedOffset, TimeZone, Utc};
+
+    use super::run;
+    use crate::{
+        error,
+        record::{Entry, Record},
+    };
+
+    #[test]
+    fn inserts_in_order_unless_overlapping() {
+        let mut record = Record::try_from(
+            "2023-01-01T00:00:00+00:00
+2023-01-01T01:00:00+00:00
+
+2023-01-01T04:00:00+00:00
+2023-01-01T05:00:00+00:00
+",
+        )
+        .unwrap()
+        .with_timezone(&Utc);
+        let entry = |start, end| {
+            Entry::try_new(
+                Utc.with_ymd_and_hms(2023, 1, 1, start, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2023, 1, 1, end, 0, 0).unwrap(),
+                None,
+                None,
+            )
+            .unwrap()
+        };
+
+        let tz = FixedOffset::east_opt(0).unwrap();
+        run(&mut record, entry(2, 3), false, &tz).unwrap();
+        assert!(matches!(
+            run(&mut record, entry(3, 5), false, &tz),
+            Err(error::Main::OverlapsSession(_))
+        ));
+        run(&mut record, entry(3, 5), true, &tz).unwrap();
+
+        let check_ins = record
+            .get_entries()
+            .iter()
+            .map(|entry| entry.check_in.format("%H").to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["00", "02", "03", "04"], check_ins);
+    }
+}
@@ -0,0 +1,30 @@
+use crate::{app::context::Context, error::Result, record::Storage, time::ContextTimeZone};
+
+pub fn list<Tz: ContextTimeZone>(ctx: &Context<Tz>, storage: &Storage) -> Result<()> {
+    let backups = storage.backups()?;
+    if backups.is_empty() {
+        println!("No backups have been taken yet.");
+        return Ok(());
+    }
+
+    for id in backups {
+        let taken = Storage::backup_time(&id).map_or_else(
+            || "unknown time".to_owned(),
+            |time| time.with_timezone(&ctx.timezone).format("%c").to_string(),
+        );
+        let summary = match storage.load_backup(&id) {
+            Ok(record) => {
+                let state = if record.get_current_session().is_some() {
+                    "clocked in"
+                } else {
+                    "clocked out"
+                };
+                format!("{} entries, {state}", record.get_entries().len())
+            }
+            Err(err) => format!("unreadable: {err}"),
+        };
+        println!("{id}  {taken}  {summary}");
+    }
+
+    Ok(())
+}
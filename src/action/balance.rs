@@ -0,0 +1,146 @@
+use std::ops::RangeInclusive;
+
+use chrono::{Duration, NaiveDate};
+
+use crate::{
+    app::context::Context,
+    error::{self, Result},
+    record::Record,
+    time::{human_readable_duration, signed_human_readable_duration, ContextTimeZone},
+};
+
+pub struct DayBalance {
+    pub date: NaiveDate,
+    pub worked: Duration,
+    pub target: Duration,
+}
+
+impl DayBalance {
+    pub fn difference(&self) -> Duration {
+        self.worked - self.target
+    }
+}
+
+/// Time worked against the scheduled target for every day in `range`
+pub fn days<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    record: &Record<Tz>,
+    range: RangeInclusive<NaiveDate>,
+) -> Result<Vec<DayBalance>> {
+    let range_start = *range.start();
+    let range_end = *range.end();
+    if range_end < range_start {
+        return Err(error::Main::RangeStartPosition);
+    }
+
+    range_start
+        .iter_days()
+        .take_while(|d| d <= &range_end)
+        .map(|date| {
+            Ok(DayBalance {
+                date,
+                worked: record.clone().days_time(ctx, date)?,
+                target: ctx.config.schedule.target(date),
+            })
+        })
+        .collect()
+}
+
+pub fn run<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    record: &Record<Tz>,
+    range: RangeInclusive<NaiveDate>,
+) -> Result<()> {
+    if !ctx.config.schedule.is_set() {
+        return Err(error::Main::NoSchedule);
+    }
+
+    println!(
+        "Balance for {} to {}",
+        range.start().format("%F"),
+        range.end().format("%F")
+    );
+
+    let mut balance = Duration::zero();
+    for day in days(ctx, record, range)? {
+        let day_off = ctx.config.schedule.day_off(day.date);
+        // Nothing to report for days off that weren't worked, like weekends
+        if day.target.is_zero() && day.worked.is_zero() && day_off.is_none() {
+            continue;
+        }
+
+        balance += day.difference();
+        println!(
+            "{:<14} {:>22} of {:<22} {:<24} {}",
+            day.date.format("%F %a"),
+            human_readable_duration(&day.worked)?,
+            day_off.map_or_else(
+                || human_readable_duration(&day.target),
+                |reason| Ok(reason.to_owned())
+            )?,
+            signed_human_readable_duration(&day.difference())?,
+            signed_human_readable_duration(&balance)?
+        );
+    }
+
+    println!(
+        "Balance: {} ({})",
+        signed_human_readable_duration(&balance)?,
+        if balance < Duration::zero() {
+            "undertime"
+        } else {
+            "overtime"
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, FixedOffset, NaiveDate};
+    use pretty_assertions::assert_eq;
+
+    use super::days;
+    use crate::{
+        app::{config::Config, context::Context},
+        record::Record,
+    };
+
+    #[test]
+    fn days_against_schedule() {
+        let config: Config = "[schedule]
+monday = \"8h\"
+tuesday = \"8h\"
+holidays = [2024-01-02]
+"
+        .parse()
+        .unwrap();
+        let tz = FixedOffset::east_opt(0).unwrap();
+        let ctx = Context::init(tz, true, config);
+        let record = Record::try_from(
+            "2024-01-01T09:00:00+00:00
+2024-01-01T18:30:00+00:00
+
+2024-01-02T09:00:00+00:00
+2024-01-02T10:00:00+00:00
+
+2024-01-03T09:00:00+00:00
+2024-01-03T11:00:00+00:00
+",
+        )
+        .unwrap();
+        let date = |day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+
+        let days = days(&ctx, &record, date(1)..=date(3)).unwrap();
+
+        assert_eq!(
+            vec![
+                Duration::minutes(90),
+                Duration::hours(1),
+                Duration::hours(2)
+            ],
+            days.iter().map(|day| day.difference()).collect::<Vec<_>>()
+        );
+    }
+}
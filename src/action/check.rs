@@ -0,0 +1,192 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, Duration, FixedOffset};
+
+use crate::{
+    app::context::Context,
+    error::{self, Result},
+    record::parse::{self, Parsed},
+    time::{human_readable_duration, ContextTimeZone},
+};
+
+/// Something wrong with the record, at the line it was found on if there is one
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+pub struct Problem {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Problem {
+    fn at(line: usize, message: String) -> Self {
+        Self {
+            line: Some(line),
+            message,
+        }
+    }
+
+    /// The same problem, found `lines` further down
+    pub fn shifted(self, lines: usize) -> Self {
+        Self {
+            line: self.line.map(|line| line + lines),
+            ..self
+        }
+    }
+}
+
+impl From<error::Main> for Problem {
+    fn from(err: error::Main) -> Self {
+        match err {
+            error::Main::RecordLine { line, text, source } => {
+                Self::at(line, format!("{source}\n{text}"))
+            }
+            err => Self {
+                line: None,
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "Line {line} of the record: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+pub struct Report {
+    pub entries: usize,
+    pub problems: Vec<Problem>,
+}
+
+/// Parses every paragraph of `source`, collecting problems instead of stopping at the first one
+pub fn check(source: &str, max_session_length: Duration) -> Result<Report> {
+    let paragraphs = parse::paragraphs(source);
+    let last = paragraphs.len().saturating_sub(1);
+
+    let mut entries = 0;
+    let mut problems = vec![];
+    // Check-in and check-out of the previous entry that parsed successfully
+    let mut previous: Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> = None;
+
+    for (i, paragraph) in paragraphs.iter().enumerate() {
+        let line = paragraph.line();
+
+        match paragraph.parse(i == last) {
+            Err(err) => problems.push(err.into()),
+            Ok(Parsed::Entry(entry)) => {
+                entries += 1;
+                let check_out = entry.get_check_out()?;
+
+                if let Some((previous_in, previous_out)) = previous {
+                    if entry.check_in < previous_in {
+                        problems.push(Problem::at(
+                            line,
+                            "entry starts before the previous entry".to_owned(),
+                        ));
+                    } else if entry.check_in < previous_out {
+                        problems.push(Problem::at(
+                            line,
+                            format!(
+                                "entry overlaps the previous entry, which ends at {}",
+                                previous_out.to_rfc3339()
+                            ),
+                        ));
+                    }
+                }
+
+                let duration = entry.get_work_duration();
+                if duration > max_session_length {
+                    problems.push(Problem::at(
+                        line,
+                        format!(
+                            "entry lasts {}, longer than the limit of {}",
+                            human_readable_duration(&duration)?,
+                            human_readable_duration(&max_session_length)?
+                        ),
+                    ));
+                }
+
+                previous = Some((entry.check_in, check_out));
+            }
+            Ok(Parsed::CurrentSession(session)) => {
+                if let Some((_, previous_out)) = previous {
+                    if session.check_in < previous_out {
+                        problems.push(Problem::at(
+                            line,
+                            format!(
+                                "current session starts before the last check-out at {}",
+                                previous_out.to_rfc3339()
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(Report { entries, problems })
+}
+
+pub fn run<Tz: ContextTimeZone>(ctx: &Context<Tz>) -> Result<()> {
+    let source = std::fs::read_to_string(".punch_clock/record")?;
+    let report = check(&source, ctx.config.check.max_session_length)?;
+
+    if report.problems.is_empty() {
+        println!("No problems found in {} entries", report.entries);
+        return Ok(());
+    }
+
+    for problem in &report.problems {
+        println!("{problem}\n");
+    }
+
+    Err(error::Main::RecordProblems(report.problems.len()))
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+    use pretty_assertions::assert_eq;
+
+    use super::check;
+
+    #[test]
+    fn reports_every_problem() {
+        let report = check(
+            "2023-01-01T00:00:00+00:00
+2023-01-01T02:00:00+00:00
+
+2023-01-01T01:00:00+00:00
+2023-01-01T03:00:00+00:00
+
+2023-01-01T04:00:00+00:00 oops
+2023-01-01T0500:00+00:00
+
+2023-01-02T00:00:00+00:00
+2023-01-03T00:00:00+00:00
+
+2023-01-02T12:00:00+00:00
+",
+            Duration::hours(16),
+        )
+        .unwrap();
+
+        assert_eq!(3, report.entries);
+        assert_eq!(
+            vec![
+                "Line 4 of the record: entry overlaps the previous entry, which ends at 2023-01-01T02:00:00+00:00",
+                "Line 8 of the record: Date parsing error: input contains invalid characters\n2023-01-01T0500:00+00:00",
+                "Line 10 of the record: entry lasts 1 days, 0 hours, 0 minutes, longer than the limit of 16 hours, 0 minutes",
+                "Line 13 of the record: current session starts before the last check-out at 2023-01-03T00:00:00+00:00",
+            ],
+            report
+                .problems
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        );
+    }
+}
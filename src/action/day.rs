@@ -0,0 +1,72 @@
+use chrono::{Days, NaiveDate};
+
+use crate::{
+    app::{
+        cli::{DayOutput, DayResolution},
+        context::Context,
+    },
+    error::{self, Result},
+    record::{self, OffsetChange, Record},
+    time::{hours_and_minutes, ContextTimeZone, NaiveDateOperations},
+};
+
+pub fn run<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    record: &Record<Tz>,
+    date: NaiveDate,
+    resolution: &DayResolution,
+    output: DayOutput,
+    offset_changes: &[OffsetChange],
+) -> Result<()> {
+    let next_date = date
+        .checked_add_days(Days::new(1))
+        .ok_or(error::Main::DateOutOfRange)?
+        .into_day_start(ctx)?;
+    let day_offset_changes = offset_changes
+        .iter()
+        .filter(|change| change.date == date)
+        .collect::<Vec<_>>();
+    let date = date.into_day_start(ctx)?;
+    let total_datetime_ranges = record
+        .clone()
+        .try_into_cropped_datetime_ranges(ctx, date, next_date)?;
+    let total = ctx.config.rounding.tally(&total_datetime_ranges);
+    let mut summary = vec![format!("Total time: {}", total.display(hours_and_minutes)?)];
+    for change in day_offset_changes {
+        summary.push(format!("Recorded {change}"));
+    }
+
+    if let DayOutput::Svg = output {
+        let break_ranges = record
+            .clone()
+            .try_into_cropped_break_ranges(ctx, date, next_date)?;
+        print!(
+            "{}",
+            record::display::svg::day_image(
+                ctx,
+                record,
+                date..=next_date,
+                &summary,
+                &total_datetime_ranges,
+                &break_ranges,
+            )?
+        );
+        return Ok(());
+    }
+
+    for line in summary {
+        println!("{line}");
+    }
+
+    // Days changing to or from DST are 23 or 25 hours long, and still get one line per hour
+    let lines = (next_date - date).num_minutes() * i64::from(resolution.as_hour_fraction());
+    let tr = record::display::time_range::time_range(
+        record,
+        ctx.timezone.now(),
+        date..=next_date,
+        u16::try_from((lines + 30) / 60)?,
+    )?;
+    println!("{}", tr.print(6, &ctx.config.formats.day)?);
+
+    Ok(())
+}
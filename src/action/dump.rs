@@ -1,11 +1,30 @@
-use crate::{error::Result, record::Record, time::ContextTimeZone};
+use crate::{
+    app::context::Context,
+    error::Result,
+    record::{Break, Record},
+    time::ContextTimeZone,
+};
 
-const DATE_FORMAT: &str = "%e %b %Y %I:%M%P %Z";
+/// A row for each break, indented under the session it was taken in
+fn print_breaks<Tz: ContextTimeZone>(breaks: &[Break<Tz>], date_format: &str) {
+    for pause in breaks {
+        let end = pause.end.as_ref().map_or("-".to_owned(), |end| {
+            format!("  Resume {}", end.format(date_format))
+        });
+        println!(
+            "| {:<40} | {:<40} |",
+            format!("  Break {}", pause.start.format(date_format)),
+            end,
+        );
+    }
+}
 
-pub fn run<Tz>(record: &Record<Tz>) -> Result<()>
+pub fn run<Tz>(ctx: &Context<Tz>, record: &Record<Tz>) -> Result<()>
 where
     Tz: ContextTimeZone,
 {
+    let date_format = ctx.config.formats.dump.as_str();
+
     println!("| {:<40} | {:<40} |", "Check-in", "Check-out");
     for _ in 0..89 {
         print!("=");
@@ -16,18 +35,20 @@ where
         let local_check_out_date = entry.get_check_out()?;
         print!(
             "| {:<40} | {:<40} |",
-            local_check_in_date.format(DATE_FORMAT),
-            local_check_out_date.format(DATE_FORMAT),
+            local_check_in_date.format(date_format),
+            local_check_out_date.format(date_format),
         );
         println!();
+        print_breaks(&entry.breaks, date_format);
     }
 
-    if let Some((current_session, _)) = record.get_current_session() {
+    if let Some(current_session) = record.get_current_session() {
         println!(
             "| {:<40} | {:<40} |",
-            current_session.format(DATE_FORMAT),
+            current_session.check_in.format(date_format),
             "-",
         );
+        print_breaks(&current_session.breaks, date_format);
     }
 
     Ok(())
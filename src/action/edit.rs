@@ -1,7 +1,9 @@
-use std::fs;
+use std::{fs, ops::Range};
 
-use chrono::{Local, Utc};
+use chrono::{Duration, Utc};
+use dialoguer::Confirm;
 
+use super::check::Problem;
 use crate::{
     app::context::Context,
     error::{self, Result},
@@ -9,21 +11,225 @@ use crate::{
     time::ContextTimeZone,
 };
 
-pub fn run<Tz: ContextTimeZone>(ctx: &Context<Tz>, record: Record<Utc>) -> Result<Record<Utc>> {
-    let record_string = record.with_timezone(&Local).serialize()?;
-    let edit_path = ".punch_clock/EDIT_RECORD";
+const EDIT_PATH: &str = ".punch_clock/EDIT_RECORD";
+/// First line of the comment block listing problems, which is how it's recognised on the next attempt
+const PROBLEMS_HEADER: &str =
+    "# The record wasn't saved because of these problems. Fix them, save and close the editor.\n";
 
-    fs::write(edit_path, record_string)?;
+fn open_editor<Tz: ContextTimeZone>(ctx: &Context<Tz>) -> Result<()> {
+    let editor_path = ctx
+        .editor_path
+        .as_deref()
+        .ok_or(error::Main::MissingEditorPath)?;
+    let mut editor_command = editor_path.split_whitespace();
+    let editor_program = editor_command
+        .next()
+        .ok_or(error::Main::MissingEditorPath)?;
 
-    if !std::process::Command::new(&ctx.editor_path)
-        .arg(edit_path)
+    if !std::process::Command::new(editor_program)
+        .args(editor_command)
+        .arg(EDIT_PATH)
         .status()?
         .success()
     {
         return Err(error::Main::UnsuccessfulEditor);
     }
 
-    let edited_record_string = fs::read_to_string(edit_path)?;
+    Ok(())
+}
+
+/// The edited text without the comment block added by a previous attempt, up to the blank line that ends it
+fn strip_problems(edited: &str) -> &str {
+    if !edited.starts_with(PROBLEMS_HEADER) {
+        return edited;
+    }
+    edited.split_once("\n\n").map_or(edited, |(_, rest)| rest)
+}
+
+fn problems_header(problems: &[Problem]) -> String {
+    let mut header = format!("{PROBLEMS_HEADER}#\n");
+    for problem in problems {
+        for line in problem.to_string().lines() {
+            header.push_str("# ");
+            header.push_str(line);
+            header.push('\n');
+        }
+    }
+    header.push('\n');
+    header
+}
+
+/// Parses `edited`, then puts it in place of the entries in `range` of `record`.
+///
+/// The outer error is for failures unrelated to what was typed, the inner one lists the problems to fix.
+fn apply<Tz: ContextTimeZone>(
+    record: &Record<Utc>,
+    range: Option<Range<usize>>,
+    edited: &str,
+    timezone: &Tz,
+) -> Result<Result<Record<Utc>, Vec<Problem>>> {
+    // Lengths are left to `check`, long sessions shouldn't get in the way of saving
+    let problems = super::check::check(edited, Duration::MAX)?.problems;
+    if !problems.is_empty() {
+        return Ok(Err(problems));
+    }
+
+    let edited = match Record::try_from(edited) {
+        Ok(edited) => edited.with_timezone(&Utc),
+        Err(err) => return Ok(Err(vec![err.into()])),
+    };
+    let Some(range) = range else {
+        return Ok(Ok(edited));
+    };
+
+    if edited.get_current_session().is_some() {
+        return Ok(Err(vec![Problem {
+            line: None,
+            message: "Sessions edited with --from or --to need a check-out".to_owned(),
+        }]));
+    }
+
+    let mut merged = record.clone();
+    match merged.replace_entries(range, edited.get_entries().to_vec(), timezone) {
+        Ok(()) => Ok(Ok(merged)),
+        Err(err) => Ok(Err(vec![err.into()])),
+    }
+}
+
+/// Opens the record, or just the entries in `range`, in the editor until it's valid.
+///
+/// Returns `None` if the user gives up, leaving `record` as it was.
+pub fn run<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    record: &Record<Utc>,
+    range: Option<Range<usize>>,
+) -> Result<Option<Record<Utc>>> {
+    let editing = match &range {
+        Some(range) => record.sub_record(range.clone()),
+        None => record.clone(),
+    };
+    fs::write(EDIT_PATH, editing.serialize()?)?;
+
+    loop {
+        open_editor(ctx)?;
+
+        let edited = fs::read_to_string(EDIT_PATH)?;
+        let body = strip_problems(&edited);
+
+        let problems = match apply(record, range.clone(), body, &ctx.timezone)? {
+            Ok(record) => {
+                fs::remove_file(EDIT_PATH)?;
+                return Ok(Some(record));
+            }
+            Err(problems) => problems,
+        };
+        // Problems are found in the body, which is written back under a header listing them
+        let header_lines = problems_header(&problems).lines().count();
+        let problems = problems
+            .into_iter()
+            .map(|problem| problem.shifted(header_lines))
+            .collect::<Vec<_>>();
 
-    Ok(Record::try_from(edited_record_string.as_str())?.with_timezone(&Utc))
+        for problem in &problems {
+            println!("{problem}");
+        }
+
+        if !Confirm::new()
+            .with_prompt("The record has problems. Re-open the editor to fix them?")
+            .default(true)
+            .interact()?
+        {
+            println!("Edit aborted, the record is unchanged.");
+            return Ok(None);
+        }
+
+        fs::write(EDIT_PATH, problems_header(&problems) + body)?;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{FixedOffset, Utc};
+    use pretty_assertions::assert_eq;
+
+    use super::{apply, problems_header, strip_problems, Problem};
+    use crate::record::Record;
+
+    const RECORD_STR: &str = "2023-01-01T00:00:00+00:00
+2023-01-01T01:00:00+00:00
+
+2023-01-02T00:00:00+00:00
+2023-01-02T01:00:00+00:00
+";
+
+    #[test]
+    fn header_round_trip() {
+        let body = "2023-01-01T00:00:00+00:00\n";
+        let problem = Problem {
+            line: Some(3),
+            message: "oops\ntext".to_owned(),
+        };
+        let edited = problems_header(&[problem]) + body;
+        assert_eq!(body, strip_problems(&edited));
+        assert_eq!(body, strip_problems(body));
+
+        // Only the generated header is removed, not other lines starting with `#`
+        let commented = format!("# A note\n\n{body}");
+        assert_eq!(commented, strip_problems(&commented));
+        let edited = problems_header(&[]) + &commented;
+        assert_eq!(commented, strip_problems(&edited));
+    }
+
+    #[test]
+    fn problem_lines_under_header() {
+        let body = "2023-01-01T00:00:00+00:00\n2023-01-01T0100:00+00:00\n";
+        let Err(problems) = apply(
+            &Record::try_from("").unwrap().with_timezone(&Utc),
+            None,
+            body,
+            &FixedOffset::east_opt(0).unwrap(),
+        )
+        .unwrap() else {
+            panic!("expected a parsing problem");
+        };
+        let header_lines = problems_header(&problems).lines().count();
+        let problem = problems.into_iter().next().unwrap().shifted(header_lines);
+
+        let edited = problems_header(std::slice::from_ref(&problem)) + body;
+        let line = edited.lines().nth(problem.line.unwrap() - 1).unwrap();
+        assert_eq!("2023-01-01T0100:00+00:00", line);
+    }
+
+    #[test]
+    fn apply_range() {
+        let record = Record::try_from(RECORD_STR).unwrap().with_timezone(&Utc);
+
+        let merged = apply(
+            &record,
+            Some(1..2),
+            "2023-01-02T00:00:00+00:00\n2023-01-02T02:00:00+00:00\n",
+            &FixedOffset::east_opt(0).unwrap(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(2, merged.get_entries().len());
+        assert_eq!(
+            "2023-01-02T02:00:00+00:00",
+            merged.get_entries()[1]
+                .get_check_out()
+                .unwrap()
+                .to_rfc3339()
+        );
+
+        let Err(problems) = apply(
+            &record,
+            Some(1..2),
+            "2023-01-01T00:30:00+00:00\n2023-01-02T02:00:00+00:00\n",
+            &FixedOffset::east_opt(0).unwrap(),
+        )
+        .unwrap() else {
+            panic!("expected an overlap");
+        };
+        assert_eq!(1, problems.len());
+    }
 }
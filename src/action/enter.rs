@@ -1,15 +1,26 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 
-use crate::{error::Result, record::Record, string::assert_no_newlines};
+use crate::{
+    error::Result,
+    record::{Labels, Record},
+    string::assert_no_newlines,
+    time::{local_display, ContextTimeZone},
+};
 
-pub fn run(record: &mut Record<Utc>, comment: Option<String>) -> Result<()> {
+pub fn run<Tz: ContextTimeZone>(
+    record: &mut Record<Utc>,
+    comment: Option<String>,
+    labels: Labels,
+    at: DateTime<Tz>,
+) -> Result<()> {
     let comment = comment.map(assert_no_newlines).transpose()?;
 
-    let clock_in_time = record.clock_in(comment)?;
+    let timezone = at.timezone();
+    let clock_in_time = record.clock_in(comment, labels, at)?;
 
     println!(
         "Clocking in on {}",
-        clock_in_time.with_timezone(&chrono::Local).format("%c")
+        local_display(&clock_in_time, &timezone)
     );
 
     Ok(())
@@ -1,17 +1,25 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 
 use crate::{
-    error::Result, record::Record, string::assert_no_newlines, time::human_readable_duration,
+    error::Result,
+    record::Record,
+    string::assert_no_newlines,
+    time::{human_readable_duration, local_display, ContextTimeZone},
 };
 
-pub fn run(record: &mut Record<Utc>, comment: Option<String>) -> Result<()> {
+pub fn run<Tz: ContextTimeZone>(
+    record: &mut Record<Utc>,
+    comment: Option<String>,
+    at: DateTime<Tz>,
+) -> Result<()> {
     let comment = comment.map(assert_no_newlines).transpose()?;
 
-    let (clock_out_time, since) = record.clock_out(comment)?;
+    let timezone = at.timezone();
+    let (clock_out_time, since) = record.clock_out(comment, at)?;
 
     println!(
         "Clocking out on {} after {}",
-        clock_out_time.with_timezone(&chrono::Local).format("%c"),
+        local_display(&clock_out_time, &timezone),
         human_readable_duration(&since)?
     );
 
@@ -0,0 +1,180 @@
+pub mod ics;
+
+use std::io::Write;
+
+use chrono::{DateTime, TimeZone};
+use serde::Serialize;
+
+use crate::{
+    app::cli::ExportFormat,
+    error::Result,
+    record::{Item, Record},
+    time::ContextTimeZone,
+};
+
+#[derive(Serialize)]
+pub struct Row {
+    pub check_in: String,
+    pub check_out: String,
+    /// The time worked, not counting breaks
+    pub duration_seconds: i64,
+    pub break_seconds: i64,
+    pub in_comment: Option<String>,
+    pub out_comment: Option<String>,
+    pub project: Option<String>,
+    pub tags: Vec<String>,
+    /// Whether this row is the still-open current session, ended at the time of export
+    pub open: bool,
+}
+
+/// Whether a session from `check_in` to `check_out` overlaps `start..end`
+fn in_range<Tz: TimeZone>(
+    check_in: &DateTime<Tz>,
+    check_out: &DateTime<Tz>,
+    start: Option<&DateTime<Tz>>,
+    end: Option<&DateTime<Tz>>,
+) -> bool {
+    start.is_none_or(|start| check_out >= start) && end.is_none_or(|end| check_in <= end)
+}
+
+/// Builds a row for every item in `record` that overlaps `start..end`, ending any current session at `end_current`
+pub fn rows<Tz: ContextTimeZone>(
+    record: Record<Tz>,
+    end_current: DateTime<Tz>,
+    start: Option<DateTime<Tz>>,
+    end: Option<DateTime<Tz>>,
+) -> Result<Vec<Row>> {
+    let mut rows = vec![];
+
+    for item in record {
+        let open = matches!(item, Item::CurrentSession(_));
+        let entry = item.into_entry(|| end_current)?;
+        let check_out = entry.get_check_out()?;
+
+        if !in_range(&entry.check_in, &check_out, start.as_ref(), end.as_ref()) {
+            continue;
+        }
+
+        rows.push(Row {
+            check_in: entry.check_in.to_rfc3339(),
+            check_out: check_out.to_rfc3339(),
+            duration_seconds: (entry.get_work_duration() - entry.get_break_duration())
+                .num_seconds(),
+            break_seconds: entry.get_break_duration().num_seconds(),
+            in_comment: entry.in_comment,
+            out_comment: entry.out_comment,
+            project: entry.labels.project,
+            tags: entry.labels.tags.into_iter().collect(),
+            open,
+        });
+    }
+
+    Ok(rows)
+}
+
+pub fn write<W: Write>(rows: &[Row], format: ExportFormat, mut out: W) -> Result<()> {
+    match format {
+        ExportFormat::Ics => unreachable!("written straight from the record by `ics::write`"),
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, rows)?;
+            writeln!(out)?;
+        }
+        ExportFormat::Ndjson => {
+            for row in rows {
+                serde_json::to_writer(&mut out, row)?;
+                writeln!(out)?;
+            }
+        }
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            writer.write_record([
+                "check_in",
+                "check_out",
+                "duration_seconds",
+                "break_seconds",
+                "in_comment",
+                "out_comment",
+                "project",
+                "tags",
+                "open",
+            ])?;
+            for row in rows {
+                writer.write_record([
+                    row.check_in.as_str(),
+                    row.check_out.as_str(),
+                    &row.duration_seconds.to_string(),
+                    &row.break_seconds.to_string(),
+                    row.in_comment.as_deref().unwrap_or_default(),
+                    row.out_comment.as_deref().unwrap_or_default(),
+                    row.project.as_deref().unwrap_or_default(),
+                    &row.tags.join(" "),
+                    &row.open.to_string(),
+                ])?;
+            }
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{FixedOffset, TimeZone};
+    use pretty_assertions::assert_eq;
+
+    use super::{rows, write};
+    use crate::{app::cli::ExportFormat, record::Record};
+
+    const RECORD_STR: &str = "2023-01-01T00:00:00+00:00 Starting
+2023-01-01T01:30:00+00:00 Done, for now
+@project acme
+@tags review
+@break 2023-01-01T00:45:00+00:00 2023-01-01T01:00:00+00:00 Coffee
+
+2023-01-02T04:00:00+00:00
+";
+
+    fn export(format: ExportFormat) -> String {
+        let tz = FixedOffset::east_opt(0).unwrap();
+        let record = Record::try_from(RECORD_STR).unwrap();
+        let end = tz.with_ymd_and_hms(2023, 1, 2, 5, 0, 0).unwrap();
+        let rows = rows(record, end, None, None).unwrap();
+        let mut buf = vec![];
+        write(&rows, format, &mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(
+            "check_in,check_out,duration_seconds,break_seconds,in_comment,out_comment,project,tags,open
+2023-01-01T00:00:00+00:00,2023-01-01T01:30:00+00:00,4500,900,Starting,\"Done, for now\",acme,review,false
+2023-01-02T04:00:00+00:00,2023-01-02T05:00:00+00:00,3600,0,,,,,true
+",
+            export(ExportFormat::Csv)
+        );
+    }
+
+    #[test]
+    fn ndjson() {
+        assert_eq!(
+            r#"{"check_in":"2023-01-01T00:00:00+00:00","check_out":"2023-01-01T01:30:00+00:00","duration_seconds":4500,"break_seconds":900,"in_comment":"Starting","out_comment":"Done, for now","project":"acme","tags":["review"],"open":false}
+{"check_in":"2023-01-02T04:00:00+00:00","check_out":"2023-01-02T05:00:00+00:00","duration_seconds":3600,"break_seconds":0,"in_comment":null,"out_comment":null,"project":null,"tags":[],"open":true}
+"#,
+            export(ExportFormat::Ndjson)
+        );
+    }
+
+    #[test]
+    fn range() {
+        let tz = FixedOffset::east_opt(0).unwrap();
+        let record = Record::try_from(RECORD_STR).unwrap();
+        let end = tz.with_ymd_and_hms(2023, 1, 2, 5, 0, 0).unwrap();
+        let start = tz.with_ymd_and_hms(2023, 1, 2, 0, 0, 0).unwrap();
+        let rows = rows(record, end, Some(start), None).unwrap();
+
+        assert_eq!(1, rows.len());
+        assert!(rows[0].open);
+    }
+}
@@ -0,0 +1,159 @@
+use std::io::Write;
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use super::in_range;
+use crate::{
+    error::Result,
+    record::{Item, Record},
+    time::ContextTimeZone,
+};
+
+/// Lines longer than this many bytes are folded onto continuation lines
+const LINE_LIMIT: usize = 75;
+
+/// Keeps the project, so importing the file again restores it
+pub const PROJECT_PROPERTY: &str = "X-PUNCH-CLOCK-PROJECT";
+
+/// Ends the `UID` of every exported event
+pub const UID_SUFFIX: &str = "@punch_clock";
+
+/// The summary of a session without a check-in comment or a project
+pub const FALLBACK_SUMMARY: &str = "Work";
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn datetime<Tz: TimeZone>(datetime: &DateTime<Tz>) -> String {
+    datetime
+        .with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+/// Writes a content line, folding it as RFC 5545 requires
+fn write_line<W: Write>(out: &mut W, line: &str) -> Result<()> {
+    let mut rest = line;
+    let mut limit = LINE_LIMIT;
+    loop {
+        if rest.len() <= limit {
+            write!(out, "{rest}\r\n")?;
+            return Ok(());
+        }
+
+        let mut split = limit;
+        while !rest.is_char_boundary(split) {
+            split -= 1;
+        }
+        write!(out, "{}\r\n ", &rest[..split])?;
+        rest = &rest[split..];
+        // The leading space of a continuation line counts towards its length
+        limit = LINE_LIMIT - 1;
+    }
+}
+
+/// Writes a `VEVENT` for every item in `record` that overlaps `start..end`.
+///
+/// The current session is ended at `now`, and marked tentative.
+pub fn write<Tz: ContextTimeZone, W: Write>(
+    record: Record<Tz>,
+    now: DateTime<Tz>,
+    start: Option<DateTime<Tz>>,
+    end: Option<DateTime<Tz>>,
+    mut out: W,
+) -> Result<()> {
+    write_line(&mut out, "BEGIN:VCALENDAR")?;
+    write_line(&mut out, "VERSION:2.0")?;
+    write_line(&mut out, "PRODID:-//punch_clock//punch_clock//EN")?;
+
+    for item in record {
+        let open = matches!(item, Item::CurrentSession(_));
+        let entry = item.into_entry(|| now)?;
+        let check_out = entry.get_check_out()?;
+        if !in_range(&entry.check_in, &check_out, start.as_ref(), end.as_ref()) {
+            continue;
+        }
+
+        let summary = entry
+            .in_comment
+            .as_deref()
+            .or(entry.labels.project.as_deref())
+            .unwrap_or(FALLBACK_SUMMARY);
+
+        write_line(&mut out, "BEGIN:VEVENT")?;
+        write_line(
+            &mut out,
+            &format!("UID:{}{UID_SUFFIX}", datetime(&entry.check_in)),
+        )?;
+        write_line(&mut out, &format!("DTSTAMP:{}", datetime(&now)))?;
+        write_line(&mut out, &format!("DTSTART:{}", datetime(&entry.check_in)))?;
+        write_line(&mut out, &format!("DTEND:{}", datetime(&check_out)))?;
+        write_line(&mut out, &format!("SUMMARY:{}", escape(summary)))?;
+        if let Some(out_comment) = &entry.out_comment {
+            write_line(&mut out, &format!("DESCRIPTION:{}", escape(out_comment)))?;
+        }
+        if let Some(project) = &entry.labels.project {
+            write_line(&mut out, &format!("{PROJECT_PROPERTY}:{}", escape(project)))?;
+        }
+        if !entry.labels.tags.is_empty() {
+            let tags = entry
+                .labels
+                .tags
+                .iter()
+                .map(|tag| escape(tag))
+                .collect::<Vec<_>>();
+            write_line(&mut out, &format!("CATEGORIES:{}", tags.join(",")))?;
+        }
+        write_line(
+            &mut out,
+            if open {
+                "STATUS:TENTATIVE"
+            } else {
+                "STATUS:CONFIRMED"
+            },
+        )?;
+        write_line(&mut out, "END:VEVENT")?;
+    }
+
+    write_line(&mut out, "END:VCALENDAR")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{FixedOffset, TimeZone};
+
+    use super::write;
+    use crate::record::Record;
+
+    #[test]
+    fn ics() {
+        let tz = FixedOffset::east_opt(13 * 3600).unwrap();
+        let record = Record::try_from(
+            "2023-01-01T09:00:00+13:00 Planning, then a long discussion about how the widget should be laid out
+2023-01-01T10:30:00+13:00 Done; for now
+@project acme
+@tags review widgets
+
+2023-01-02T08:00:00+13:00
+",
+        )
+        .unwrap();
+        let now = tz.with_ymd_and_hms(2023, 1, 2, 9, 0, 0).unwrap();
+
+        let mut buf = vec![];
+        write(record, now, None, None, &mut buf).unwrap();
+        let ics = String::from_utf8(buf).unwrap();
+
+        assert!(ics.ends_with("\r\n"));
+        assert!(ics
+            .split_terminator("\r\n")
+            .all(|line| line.len() <= 75 && !line.contains('\n')));
+        insta::assert_snapshot!(ics.replace("\r\n", "\n"));
+    }
+}
@@ -0,0 +1,203 @@
+use std::io::IsTerminal;
+
+use chrono::{DateTime, Utc};
+use dialoguer::{Input, Select};
+
+use crate::{
+    action::exit,
+    app::{cli::At, config::Idle, context::Context},
+    error::Result,
+    record::Record,
+    time::{human_readable_duration, local_display, ContextTimeZone},
+};
+
+const ACTIVITY_PATH: &str = ".punch_clock/activity";
+
+/// Notes the current time as the last known activity
+pub fn ping() -> Result<()> {
+    std::fs::write(ACTIVITY_PATH, Utc::now().to_rfc3339())?;
+    Ok(())
+}
+
+fn last_activity() -> Result<Option<DateTime<Utc>>> {
+    match std::fs::read_to_string(ACTIVITY_PATH) {
+        Ok(text) => Ok(DateTime::parse_from_rfc3339(text.trim())
+            .ok()
+            .map(|activity| activity.with_timezone(&Utc))),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Why an open session looks forgotten
+#[derive(Debug, PartialEq, Eq)]
+pub struct Overdue {
+    /// When the session reached `idle.max_session`, if it has
+    pub limit: Option<DateTime<Utc>>,
+    /// The last activity during the session, if it has been idle for longer than `idle.after`
+    pub idle_since: Option<DateTime<Utc>>,
+}
+
+impl Overdue {
+    pub fn check(
+        idle: &Idle,
+        check_in: DateTime<Utc>,
+        now: DateTime<Utc>,
+        last_activity: Option<DateTime<Utc>>,
+    ) -> Option<Self> {
+        let limit = idle
+            .max_session
+            .map(|max_session| check_in + max_session)
+            .filter(|limit| *limit < now);
+        // Activity from before the session started doesn't count
+        let active_since = last_activity.map_or(check_in, |activity| activity.max(check_in));
+        let idle_since = idle
+            .after
+            .filter(|after| now - active_since > *after)
+            .map(|_| active_since);
+
+        (limit.is_some() || idle_since.is_some()).then_some(Self { limit, idle_since })
+    }
+}
+
+/// Offers to close the current session if it looks forgotten, returning whether it was closed
+pub fn run<Tz: ContextTimeZone>(ctx: &Context<Tz>, record: &mut Record<Utc>) -> Result<bool> {
+    let Some(session) = record.get_current_session() else {
+        return Ok(false);
+    };
+    let check_in = session.check_in;
+    let now = Utc::now();
+    let Some(overdue) = Overdue::check(&ctx.config.idle, check_in, now, last_activity()?) else {
+        return Ok(false);
+    };
+
+    eprintln!(
+        "The current session has been open since {} ({})",
+        local_display(&check_in, &ctx.timezone),
+        human_readable_duration(&(now - check_in))?
+    );
+    if let Some(max_session) = ctx.config.idle.max_session {
+        if overdue.limit.is_some() {
+            eprintln!(
+                "That's longer than the limit of {}",
+                human_readable_duration(&max_session)?
+            );
+        }
+    }
+    if let Some(idle_since) = overdue.idle_since {
+        let since = if idle_since == check_in {
+            "since it started".to_owned()
+        } else {
+            format!("since {}", local_display(&idle_since, &ctx.timezone))
+        };
+        eprintln!("There has been no activity {since}");
+    }
+
+    if !std::io::stdin().is_terminal() {
+        eprintln!("Leaving it open; use `out --at` to close it");
+        return Ok(false);
+    }
+
+    let mut choices = vec![];
+    if let Some(limit) = overdue.limit {
+        choices.push((
+            format!(
+                "Clock out at the limit, {}",
+                local_display(&limit, &ctx.timezone)
+            ),
+            Some(limit),
+        ));
+    }
+    if let Some(idle_since) = overdue.idle_since.filter(|idle| *idle != check_in) {
+        choices.push((
+            format!(
+                "Clock out at the last activity, {}",
+                local_display(&idle_since, &ctx.timezone)
+            ),
+            Some(idle_since),
+        ));
+    }
+    choices.push(("Clock out at another time".to_owned(), None));
+    let leave = choices.len();
+
+    let labels = choices
+        .iter()
+        .map(|(label, _)| label.as_str())
+        .chain(["Leave it open"])
+        .collect::<Vec<_>>();
+    let choice = Select::new()
+        .with_prompt("Close the session?")
+        .items(&labels)
+        .default(0)
+        .interact()?;
+    if choice == leave {
+        return Ok(false);
+    }
+
+    let at = match choices[choice].1 {
+        Some(at) => at.with_timezone(&ctx.timezone),
+        None => {
+            let at = Input::<String>::new()
+                .with_prompt(
+                    "Clock out at (HH:MM on the day the session started, or YYYY-MM-DD HH:MM)",
+                )
+                .validate_with(|value: &String| {
+                    At::parse(value)
+                        .map(|_| ())
+                        .ok_or("Expected HH:MM or YYYY-MM-DD HH:MM")
+                })
+                .interact_text()?;
+            let at = At::parse(&at).expect("validated by the prompt");
+            let day = check_in.with_timezone(&ctx.timezone).date_naive();
+            at.resolve_on(ctx, day)?
+        }
+    };
+
+    exit::run(record, None, at)?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{DateTime, Duration, Utc};
+    use pretty_assertions::assert_eq;
+
+    use super::Overdue;
+    use crate::app::config::Idle;
+
+    #[test]
+    fn overdue() {
+        let check_in: DateTime<Utc> = "2024-01-01T09:00:00Z".parse().unwrap();
+        let idle = Idle {
+            max_session: Some(Duration::hours(12)),
+            after: Some(Duration::hours(2)),
+        };
+        let hours = |hours| check_in + Duration::hours(hours);
+
+        assert_eq!(None, Overdue::check(&idle, check_in, hours(1), None));
+        assert_eq!(
+            None,
+            Overdue::check(&idle, check_in, hours(3), Some(hours(2)))
+        );
+        // Activity from before the session doesn't hold it open
+        assert_eq!(
+            Some(Overdue {
+                limit: None,
+                idle_since: Some(check_in),
+            }),
+            Overdue::check(&idle, check_in, hours(3), Some(hours(-1)))
+        );
+        assert_eq!(
+            Some(Overdue {
+                limit: Some(hours(12)),
+                idle_since: Some(hours(8)),
+            }),
+            Overdue::check(&idle, check_in, hours(14), Some(hours(8)))
+        );
+        assert_eq!(
+            None,
+            Overdue::check(&Idle::default(), check_in, hours(100), None)
+        );
+    }
+}
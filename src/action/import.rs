@@ -0,0 +1,112 @@
+mod ics;
+mod timewarrior;
+mod toggl;
+mod watson;
+
+use std::{fs, path::Path};
+
+use chrono::Utc;
+
+use crate::{
+    app::{cli::ImportFormat, context::Context},
+    error::{self, Result},
+    record::{Entry, Labels, Record},
+    time::{local_display, ContextTimeZone},
+};
+
+fn invalid(format: &'static str, message: String) -> error::Main {
+    error::Main::InvalidImport { format, message }
+}
+
+/// Comments in the record must fit on one line
+fn comment(comment: String) -> String {
+    comment.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Tags in the record must not contain whitespace
+fn labels(project: Option<String>, tags: Vec<String>) -> Result<Labels> {
+    let tags = tags
+        .into_iter()
+        .map(|tag| tag.split_whitespace().collect::<Vec<_>>().join("-"))
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    Labels::try_new(project.map(comment), tags)
+}
+
+fn print_skipped<Tz: ContextTimeZone>(
+    heading: &str,
+    entries: &[Entry<Utc>],
+    timezone: &Tz,
+) -> Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    println!("{heading}:");
+    for entry in entries {
+        println!(
+            "  {} -> {}",
+            local_display(&entry.check_in, timezone),
+            local_display(&entry.get_check_out()?, timezone)
+        );
+    }
+
+    Ok(())
+}
+
+pub fn run<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    record: &mut Record<Utc>,
+    format: ImportFormat,
+    path: &Path,
+    dry_run: bool,
+) -> Result<()> {
+    let input = fs::read_to_string(path)?;
+
+    let (entries, open) = match format {
+        ImportFormat::Timewarrior => {
+            let export = timewarrior::parse(&input)?;
+            (export.entries, export.open)
+        }
+        ImportFormat::Watson => (watson::parse(&input)?, vec![]),
+        ImportFormat::TogglCsv => (toggl::parse(&input, &ctx.timezone)?, vec![]),
+        ImportFormat::Ics => (ics::parse(&input, &ctx.timezone)?, vec![]),
+    };
+    let found = entries.len();
+
+    let merged = record.merge(
+        entries
+            .into_iter()
+            .map(|entry| entry.with_timezone(&Utc))
+            .collect(),
+    )?;
+
+    if dry_run {
+        print!("{}", record.serialize()?);
+        println!();
+    }
+
+    println!(
+        "Found {found} sessions; {} {}imported",
+        merged.added,
+        if dry_run { "would be " } else { "" }
+    );
+    print_skipped(
+        "Skipped duplicate sessions",
+        &merged.duplicates,
+        &ctx.timezone,
+    )?;
+    print_skipped(
+        "Skipped sessions overlapping the existing record",
+        &merged.overlaps,
+        &ctx.timezone,
+    )?;
+    if !open.is_empty() {
+        println!("Skipped open intervals:");
+        for start in &open {
+            println!("  {} -> still running", local_display(start, &ctx.timezone));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,397 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDateTime, TimeZone, Utc};
+
+use super::{comment, invalid, labels};
+use crate::{
+    action::export::ics::{FALLBACK_SUMMARY, PROJECT_PROPERTY, UID_SUFFIX},
+    error::Result,
+    record::Entry,
+    time::ContextTimeZone,
+};
+
+const FORMAT: &str = "ics";
+
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Joins folded lines back together
+fn unfold(input: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in input.lines() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_owned()),
+        }
+    }
+    lines
+}
+
+/// Splits `NAME;PARAM=value:VALUE`, where quoted parameter values may contain `:` and `;`
+fn parse_property(line: &str) -> Result<Property> {
+    let mut in_quotes = false;
+    let colon = line
+        .char_indices()
+        .find(|&(_, c)| {
+            if c == '"' {
+                in_quotes = !in_quotes;
+            }
+            c == ':' && !in_quotes
+        })
+        .map(|(i, _)| i)
+        .ok_or_else(|| invalid(FORMAT, format!("not a content line: {line:?}")))?;
+
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = head.split(';');
+    let name = parts.next().unwrap_or_default().to_ascii_uppercase();
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(param, value)| (param.to_owned(), value.trim_matches('"').to_owned()))
+        .collect();
+
+    Ok(Property {
+        name,
+        params,
+        value: value.to_owned(),
+    })
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, c == '\\') {
+            (_, true) => match chars.next() {
+                Some('n' | 'N') => unescaped.push('\n'),
+                Some(escaped) => unescaped.push(escaped),
+                None => {}
+            },
+            (c, false) => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Parses a `DATE-TIME` property, returning `None` for a `DATE`, which has no time of day.
+///
+/// Times without `Z` are read in their `TZID`, which must be a tz database name, or else in `timezone`.
+fn parse_datetime<Tz: ContextTimeZone>(
+    property: &Property,
+    timezone: &Tz,
+) -> Result<Option<DateTime<FixedOffset>>> {
+    let value = property.value.as_str();
+    if property.param("VALUE") == Some("DATE") || !value.contains('T') {
+        return Ok(None);
+    }
+
+    let invalid_datetime = |message: String| invalid(FORMAT, format!("{value:?}: {message}"));
+
+    if let Some(utc) = value.strip_suffix('Z') {
+        let datetime = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+            .map_err(|err| invalid_datetime(err.to_string()))?;
+        return Ok(Some(Utc.from_utc_datetime(&datetime).fixed_offset()));
+    }
+
+    let datetime = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .map_err(|err| invalid_datetime(err.to_string()))?;
+    let tzid = property
+        .param("TZID")
+        .map(|tzid| {
+            chrono_tz::Tz::from_str(tzid)
+                .map_err(|_| invalid_datetime(format!("unknown TZID {tzid:?}")))
+        })
+        .transpose()?;
+    let local = match tzid {
+        Some(tz) => datetime
+            .and_local_timezone(tz)
+            .earliest()
+            .map(|d| d.fixed_offset()),
+        None => datetime
+            .and_local_timezone(*timezone)
+            .earliest()
+            .map(|d| d.fixed_offset()),
+    };
+    local
+        .map(Some)
+        .ok_or_else(|| invalid_datetime("does not exist in its timezone".to_owned()))
+}
+
+/// Parses durations like `PT1H30M` or `P1DT2H`
+fn parse_duration(value: &str) -> Option<Duration> {
+    let rest = value.strip_prefix('P')?;
+    let mut total = Duration::zero();
+    let mut in_time = false;
+    let mut digits = String::new();
+    for c in rest.chars() {
+        match c {
+            'T' => in_time = true,
+            '0'..='9' => digits.push(c),
+            unit => {
+                let amount: i64 = std::mem::take(&mut digits).parse().ok()?;
+                total += match (unit, in_time) {
+                    ('W', false) => Duration::try_weeks(amount)?,
+                    ('D', false) => Duration::try_days(amount)?,
+                    ('H', true) => Duration::try_hours(amount)?,
+                    ('M', true) => Duration::try_minutes(amount)?,
+                    ('S', true) => Duration::try_seconds(amount)?,
+                    _ => return None,
+                };
+            }
+        }
+    }
+    digits.is_empty().then_some(total)
+}
+
+#[derive(Default)]
+struct Event {
+    uid: Option<String>,
+    start: Option<DateTime<FixedOffset>>,
+    end: Option<DateTime<FixedOffset>>,
+    duration: Option<Duration>,
+    summary: Option<String>,
+    description: Option<String>,
+    project: Option<String>,
+    tags: Vec<String>,
+    cancelled: bool,
+    all_day: bool,
+}
+
+impl Event {
+    fn into_entry(self) -> Result<Option<Entry<FixedOffset>>> {
+        if self.cancelled || self.all_day {
+            return Ok(None);
+        }
+
+        let start = self
+            .start
+            .ok_or_else(|| invalid(FORMAT, "event without DTSTART".to_owned()))?;
+        let end = match (self.end, self.duration) {
+            (Some(end), _) => end,
+            (None, Some(duration)) => start + duration,
+            (None, None) => {
+                return Err(invalid(
+                    FORMAT,
+                    format!("event at {start} has neither DTEND nor DURATION"),
+                ))
+            }
+        };
+
+        // Events exported by punch_clock fall back to a summary of the project, or a placeholder
+        let exported = self
+            .uid
+            .as_deref()
+            .is_some_and(|uid| uid.ends_with(UID_SUFFIX));
+        let summary = self.summary.filter(|summary| {
+            !(exported && (summary == FALLBACK_SUMMARY || Some(summary) == self.project.as_ref()))
+        });
+
+        Ok(Some(
+            Entry::try_new(
+                start,
+                end,
+                summary.map(comment),
+                self.description.map(comment),
+            )?
+            .with_labels(labels(self.project, self.tags)?),
+        ))
+    }
+}
+
+/// Parses the `VEVENT`s of an iCalendar file, skipping cancelled and all-day events
+///
+/// Properties of components nested in an event, like a `VALARM`'s `DESCRIPTION`, are ignored.
+pub fn parse<Tz: ContextTimeZone>(input: &str, timezone: &Tz) -> Result<Vec<Entry<FixedOffset>>> {
+    let mut entries = vec![];
+    let mut event: Option<Event> = None;
+    // How many components deep inside the current event we are
+    let mut nested = 0usize;
+
+    for line in unfold(input) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let property = parse_property(&line)?;
+
+        match (property.name.as_str(), &mut event) {
+            ("BEGIN", Some(_)) => nested += 1,
+            ("END", Some(_)) if nested > 0 => nested -= 1,
+            (_, Some(_)) if nested > 0 => {}
+            ("BEGIN", None) if property.value.eq_ignore_ascii_case("VEVENT") => {
+                event = Some(Event::default());
+            }
+            ("END", Some(_)) if property.value.eq_ignore_ascii_case("VEVENT") => {
+                if let Some(entry) = event
+                    .take()
+                    .and_then(|event| event.into_entry().transpose())
+                {
+                    entries.push(entry?);
+                }
+            }
+            (_, None) => {}
+            ("UID", Some(event)) => event.uid = Some(property.value),
+            ("DTSTART", Some(event)) => match parse_datetime(&property, timezone)? {
+                Some(start) => event.start = Some(start),
+                None => event.all_day = true,
+            },
+            ("DTEND", Some(event)) => event.end = parse_datetime(&property, timezone)?,
+            ("DURATION", Some(event)) => {
+                event.duration = Some(parse_duration(&property.value).ok_or_else(|| {
+                    invalid(FORMAT, format!("invalid DURATION: {:?}", property.value))
+                })?);
+            }
+            ("SUMMARY", Some(event)) => event.summary = Some(unescape(&property.value)),
+            ("DESCRIPTION", Some(event)) => {
+                event.description = Some(unescape(&property.value));
+            }
+            ("CATEGORIES", Some(event)) => event.tags.extend(
+                property
+                    .value
+                    .split(',')
+                    .map(unescape)
+                    .filter(|tag| !tag.is_empty()),
+            ),
+            ("STATUS", Some(event)) => {
+                event.cancelled = property.value.eq_ignore_ascii_case("CANCELLED");
+            }
+            (name, Some(event)) if name == PROJECT_PROPERTY => {
+                event.project = Some(unescape(&property.value));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{FixedOffset, TimeZone};
+    use pretty_assertions::assert_eq;
+
+    use crate::{action::export, record::Record};
+
+    #[test]
+    fn parse() {
+        let entries = super::parse(
+            "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+UID:abc@example.com\r
+DTSTART;TZID=Pacific/Auckland:20231016T090000\r
+DTEND;TZID=Pacific/Auckland:20231016T101500\r
+SUMMARY:Planning\\, with a very long summary that has been folded onto a second\r
+  line\r
+CATEGORIES:meetings,planning\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART:20231016T230000\r
+DURATION:PT1H30M\r
+DESCRIPTION:Wrapped up\\nfor the day\r
+BEGIN:VALARM\r
+ACTION:DISPLAY\r
+TRIGGER:-PT15M\r
+DURATION:PT5M\r
+DESCRIPTION:Reminder\r
+END:VALARM\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20231017\r
+SUMMARY:Holiday\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART:20231018T090000Z\r
+DTEND:20231018T100000Z\r
+STATUS:CANCELLED\r
+END:VEVENT\r
+END:VCALENDAR\r
+",
+            &FixedOffset::east_opt(3600).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(2, entries.len());
+        assert_eq!(
+            "2023-10-16T09:00:00+13:00",
+            entries[0].check_in.to_rfc3339()
+        );
+        assert_eq!(75, entries[0].get_work_duration().num_minutes());
+        assert_eq!(
+            Some("Planning, with a very long summary that has been folded onto a second line"),
+            entries[0].in_comment.as_deref()
+        );
+        assert_eq!(
+            vec!["meetings", "planning"],
+            entries[0].labels.tags.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "2023-10-16T23:00:00+01:00",
+            entries[1].check_in.to_rfc3339()
+        );
+        assert_eq!(90, entries[1].get_work_duration().num_minutes());
+        assert_eq!(
+            Some("Wrapped up for the day"),
+            entries[1].out_comment.as_deref()
+        );
+
+        let mut record = Record::try_from("").unwrap();
+        for entry in entries {
+            record.insert(entry);
+        }
+        insta::assert_snapshot!(record.serialize().unwrap());
+    }
+
+    #[test]
+    fn unknown_tzid() {
+        let result = super::parse(
+            "BEGIN:VEVENT\r
+DTSTART;TZID=Pacific Standard Time:20231016T090000\r
+DTEND;TZID=Pacific Standard Time:20231016T100000\r
+END:VEVENT\r
+",
+            &FixedOffset::east_opt(0).unwrap(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let tz = FixedOffset::east_opt(0).unwrap();
+        let source = "2023-01-01T09:00:00+00:00 Planning, then a break; then more planning
+2023-01-01T10:30:00+00:00 Done
+@project acme
+@tags review widgets
+
+2023-01-01T11:00:00+00:00
+2023-01-01T12:00:00+00:00
+@project acme
+";
+        let record = Record::try_from(source).unwrap();
+        let now = tz.with_ymd_and_hms(2023, 1, 2, 0, 0, 0).unwrap();
+        let mut buf = vec![];
+        export::ics::write(record, now, None, None, &mut buf).unwrap();
+
+        let entries = super::parse(&String::from_utf8(buf).unwrap(), &tz).unwrap();
+        let mut imported = Record::try_from("").unwrap();
+        for entry in entries {
+            imported.insert(entry);
+        }
+
+        assert_eq!(
+            Record::try_from(source).unwrap().serialize().unwrap(),
+            imported.serialize().unwrap()
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
+use serde::Deserialize;
+
+use super::{comment, invalid, labels};
+use crate::{error::Result, record::Entry};
+
+const FORMAT: &str = "timewarrior";
+
+/// An interval, as printed by `timew export`
+#[derive(Deserialize)]
+struct Interval {
+    start: String,
+    end: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    annotation: Option<String>,
+}
+
+/// The closed intervals of an export, and the starts of open ones, which have no end to import
+pub struct Export {
+    pub entries: Vec<Entry<FixedOffset>>,
+    pub open: Vec<DateTime<FixedOffset>>,
+}
+
+fn parse_datetime(datetime: &str) -> Result<DateTime<FixedOffset>> {
+    let datetime = NaiveDateTime::parse_from_str(datetime, "%Y%m%dT%H%M%SZ")
+        .map_err(|err| invalid(FORMAT, format!("{datetime:?}: {err}")))?;
+    Ok(datetime.and_utc().fixed_offset())
+}
+
+/// Parses the JSON output of `timew export`, setting open intervals aside
+pub fn parse(input: &str) -> Result<Export> {
+    let intervals: Vec<Interval> =
+        serde_json::from_str(input).map_err(|err| invalid(FORMAT, err.to_string()))?;
+
+    let mut export = Export {
+        entries: vec![],
+        open: vec![],
+    };
+    for interval in intervals {
+        let start = parse_datetime(&interval.start)?;
+        let Some(end) = interval.end else {
+            export.open.push(start);
+            continue;
+        };
+        export.entries.push(
+            Entry::try_new(
+                start,
+                parse_datetime(&end)?,
+                interval.annotation.map(comment),
+                None,
+            )?
+            .with_labels(labels(None, interval.tags)?),
+        );
+    }
+
+    Ok(export)
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse() {
+        let super::Export { entries, open } = super::parse(
+            r#"[
+{"id":3,"start":"20231016T090000Z","end":"20231016T103000Z","tags":["acme","code review"],"annotation":"Reviewing\nthings"},
+{"id":2,"start":"20231016T110000Z","end":"20231016T113000Z"},
+{"id":1,"start":"20231016T120000Z","tags":["open"]}
+]"#,
+        )
+        .unwrap();
+
+        assert_eq!(2, entries.len());
+        assert_eq!(
+            "2023-10-16T09:00:00+00:00",
+            entries[0].check_in.to_rfc3339()
+        );
+        assert_eq!(90, entries[0].get_work_duration().num_minutes());
+        assert_eq!(Some("Reviewing things"), entries[0].in_comment.as_deref());
+        assert_eq!(
+            vec!["acme", "code-review"],
+            entries[0].labels.tags.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(None, entries[1].in_comment);
+        assert_eq!(
+            vec!["2023-10-16T12:00:00+00:00"],
+            open.iter()
+                .map(|start| start.to_rfc3339())
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use chrono::{FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::Deserialize;
+
+use super::{comment, invalid, labels};
+use crate::{error::Result, record::Entry, time::ContextTimeZone};
+
+const FORMAT: &str = "toggl-csv";
+
+/// A row of a Toggl Track "detailed report" CSV export
+#[derive(Deserialize)]
+struct Row {
+    #[serde(rename = "Project")]
+    project: Option<String>,
+    #[serde(rename = "Description")]
+    description: Option<String>,
+    #[serde(rename = "Start date")]
+    start_date: String,
+    #[serde(rename = "Start time")]
+    start_time: String,
+    #[serde(rename = "End date")]
+    end_date: String,
+    #[serde(rename = "End time")]
+    end_time: String,
+    #[serde(rename = "Tags")]
+    tags: Option<String>,
+}
+
+fn parse_datetime<Tz: ContextTimeZone>(
+    timezone: &Tz,
+    date: &str,
+    time: &str,
+) -> Result<chrono::DateTime<FixedOffset>> {
+    let date = NaiveDate::parse_from_str(date, "%F")
+        .map_err(|err| invalid(FORMAT, format!("{date:?}: {err}")))?;
+    let time = NaiveTime::parse_from_str(time, "%T")
+        .map_err(|err| invalid(FORMAT, format!("{time:?}: {err}")))?;
+    let datetime = NaiveDateTime::new(date, time);
+    datetime
+        .and_local_timezone(*timezone)
+        .earliest()
+        .map(|datetime| datetime.fixed_offset())
+        .ok_or_else(|| {
+            invalid(
+                FORMAT,
+                format!("{datetime} does not exist in this timezone"),
+            )
+        })
+}
+
+/// Parses a Toggl Track detailed CSV export. Its times are local, so they're read in `timezone`.
+pub fn parse<Tz: ContextTimeZone>(input: &str, timezone: &Tz) -> Result<Vec<Entry<FixedOffset>>> {
+    csv::Reader::from_reader(input.as_bytes())
+        .deserialize::<Row>()
+        .map(|row| {
+            let row = row.map_err(|err| invalid(FORMAT, err.to_string()))?;
+            let tags = row
+                .tags
+                .iter()
+                .flat_map(|tags| tags.split(','))
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(ToOwned::to_owned)
+                .collect();
+
+            Ok(Entry::try_new(
+                parse_datetime(timezone, &row.start_date, &row.start_time)?,
+                parse_datetime(timezone, &row.end_date, &row.end_time)?,
+                row.description.filter(|d| !d.is_empty()).map(comment),
+                None,
+            )?
+            .with_labels(labels(row.project, tags)?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::FixedOffset;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse() {
+        let entries = super::parse(
+            "User,Email,Client,Project,Task,Description,Billable,Start date,Start time,End date,End time,Duration,Tags,Amount ()
+Jo,jo@example.com,Acme Ltd,Website,,Fixing the header,Yes,2023-10-16,09:00:00,2023-10-16,10:15:00,01:15:00,\"frontend, bug fix\",
+Jo,jo@example.com,,,,,No,2023-10-16,23:30:00,2023-10-17,00:30:00,01:00:00,,
+",
+            &FixedOffset::east_opt(13 * 3600).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(2, entries.len());
+        assert_eq!(
+            "2023-10-16T09:00:00+13:00",
+            entries[0].check_in.to_rfc3339()
+        );
+        assert_eq!(75, entries[0].get_work_duration().num_minutes());
+        assert_eq!(Some("Fixing the header"), entries[0].in_comment.as_deref());
+        assert_eq!(Some("Website"), entries[0].labels.project.as_deref());
+        assert_eq!(
+            vec!["bug-fix", "frontend"],
+            entries[0].labels.tags.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(60, entries[1].get_work_duration().num_minutes());
+        assert_eq!(None, entries[1].labels.project);
+    }
+}
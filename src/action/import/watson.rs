@@ -0,0 +1,114 @@
+use chrono::{DateTime, FixedOffset};
+use serde::Deserialize;
+
+use super::{comment, invalid, labels};
+use crate::{error::Result, record::Entry};
+
+const FORMAT: &str = "watson";
+
+/// Either a frame as printed by `watson log --json`, or as stored in Watson's `frames` file
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Frame {
+    Log {
+        project: String,
+        start: String,
+        stop: String,
+        #[serde(default)]
+        tags: Vec<String>,
+        note: Option<String>,
+    },
+    Stored(
+        i64,
+        i64,
+        String,
+        String,
+        #[serde(default)] Vec<String>,
+        #[serde(default)] i64,
+    ),
+}
+
+fn from_timestamp(timestamp: i64) -> Result<DateTime<FixedOffset>> {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|datetime| datetime.fixed_offset())
+        .ok_or_else(|| invalid(FORMAT, format!("timestamp out of range: {timestamp}")))
+}
+
+fn from_rfc3339(datetime: &str) -> Result<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(datetime)
+        .map_err(|err| invalid(FORMAT, format!("{datetime:?}: {err}")))
+}
+
+/// Parses the output of `watson log --json`, or Watson's `frames` file
+pub fn parse(input: &str) -> Result<Vec<Entry<FixedOffset>>> {
+    let frames: Vec<Frame> =
+        serde_json::from_str(input).map_err(|err| invalid(FORMAT, err.to_string()))?;
+
+    frames
+        .into_iter()
+        .map(|frame| {
+            let (start, stop, project, tags, note) = match frame {
+                Frame::Log {
+                    project,
+                    start,
+                    stop,
+                    tags,
+                    note,
+                } => (
+                    from_rfc3339(&start)?,
+                    from_rfc3339(&stop)?,
+                    project,
+                    tags,
+                    note,
+                ),
+                Frame::Stored(start, stop, project, _id, tags, _updated_at) => (
+                    from_timestamp(start)?,
+                    from_timestamp(stop)?,
+                    project,
+                    tags,
+                    None,
+                ),
+            };
+
+            Ok(Entry::try_new(start, stop, note.map(comment), None)?
+                .with_labels(labels(Some(project), tags)?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_log() {
+        let entries = super::parse(
+            r#"[{"id":"a1","project":"acme","start":"2023-10-16T09:00:00+02:00","stop":"2023-10-16T10:00:00+02:00","tags":["review"]}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(1, entries.len());
+        assert_eq!(
+            "2023-10-16T09:00:00+02:00",
+            entries[0].check_in.to_rfc3339()
+        );
+        assert_eq!(60, entries[0].get_work_duration().num_minutes());
+        assert_eq!(Some("acme"), entries[0].labels.project.as_deref());
+        assert!(entries[0].labels.tags.contains("review"));
+    }
+
+    #[test]
+    fn parse_frames() {
+        let entries =
+            super::parse(r#"[[1697439600, 1697443200, "acme", "a1", ["review"], 1697443200]]"#)
+                .unwrap();
+
+        assert_eq!(1, entries.len());
+        assert_eq!(
+            "2023-10-16T07:00:00+00:00",
+            entries[0].check_in.to_rfc3339()
+        );
+        assert_eq!(60, entries[0].get_work_duration().num_minutes());
+        assert_eq!(Some("acme"), entries[0].labels.project.as_deref());
+    }
+}
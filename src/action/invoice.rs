@@ -0,0 +1,354 @@
+use std::{fmt::Write, ops::RangeInclusive};
+
+use chrono::{Days, Duration, NaiveDate};
+
+use crate::{
+    app::{
+        cli::{InvoiceFormat, RoundPer},
+        context::Context,
+    },
+    error::{self, Result},
+    money::Amount,
+    record::Record,
+    string::escape_html,
+    time::{rounding::Rounding, ContextTimeZone, NaiveDateOperations},
+};
+
+pub struct Line {
+    pub date: NaiveDate,
+    pub duration: Duration,
+    pub description: String,
+    pub amount: Amount,
+}
+
+pub struct Invoice {
+    pub period: RangeInclusive<NaiveDate>,
+    pub rate: Amount,
+    pub currency: Option<String>,
+    pub rounding: Option<(Rounding, RoundPer)>,
+    pub lines: Vec<Line>,
+    /// Billed time before rounding the total
+    pub unrounded_total: Duration,
+    pub total: Duration,
+    pub amount: Amount,
+}
+
+/// Sessions are cropped to `period`, and the still-open session isn't billed
+pub fn build<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    record: &Record<Tz>,
+    period: RangeInclusive<NaiveDate>,
+    rate: Amount,
+    currency: Option<String>,
+    rounding: Option<(Rounding, RoundPer)>,
+) -> Result<Invoice> {
+    if period.end() < period.start() {
+        return Err(error::Main::RangeStartPosition);
+    }
+    let start = period.start().into_day_start(ctx)?;
+    let end = period
+        .end()
+        .checked_add_days(Days::new(1))
+        .ok_or(error::Main::DateOutOfRange)?
+        .into_day_start(ctx)?;
+
+    let mut lines = vec![];
+    for (i, entry) in record.get_entries().iter().enumerate() {
+        let datetime_ranges = record
+            .sub_record(i..i + 1)
+            .try_into_cropped_datetime_ranges(ctx, start, end)?;
+        let Some(first) = datetime_ranges.first() else {
+            continue;
+        };
+        let date = first.start().date_naive();
+
+        let mut duration: Duration = datetime_ranges.into_iter().sum();
+        if let Some((rounding, RoundPer::Session)) = rounding {
+            duration = rounding.apply(duration);
+        }
+
+        let description = [&entry.in_comment, &entry.out_comment]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        lines.push(Line {
+            date,
+            duration,
+            description,
+            amount: rate.for_duration(duration),
+        });
+    }
+
+    let unrounded_total: Duration = lines.iter().map(|line| line.duration).sum();
+    let (total, amount) = match rounding {
+        Some((rounding, RoundPer::Total)) => {
+            let total = rounding.apply(unrounded_total);
+            (total, rate.for_duration(total))
+        }
+        _ => (unrounded_total, lines.iter().map(|line| line.amount).sum()),
+    };
+
+    Ok(Invoice {
+        period,
+        rate,
+        currency,
+        rounding,
+        lines,
+        unrounded_total,
+        total,
+        amount,
+    })
+}
+
+/// Like `1:05`, for 1 hour and 5 minutes
+fn hours_minutes(duration: Duration) -> String {
+    format!(
+        "{}:{:02}",
+        duration.num_hours(),
+        duration.num_minutes() % 60
+    )
+}
+
+impl Invoice {
+    fn money(&self, amount: Amount) -> String {
+        match &self.currency {
+            Some(currency) => format!("{amount} {currency}"),
+            None => amount.to_string(),
+        }
+    }
+
+    fn rounding_note(&self) -> Option<String> {
+        self.rounding.map(|(rounding, per)| {
+            let per = match per {
+                RoundPer::Session => "each session",
+                RoundPer::Total => "the total",
+            };
+            format!("Rounded {rounding} for {per}")
+        })
+    }
+
+    fn total_note(&self) -> String {
+        let mut note = format!(
+            "Total: {} hours, {}",
+            hours_minutes(self.total),
+            self.money(self.amount)
+        );
+        if self.total != self.unrounded_total {
+            note.push_str(&format!(
+                " ({} hours before rounding)",
+                hours_minutes(self.unrounded_total)
+            ));
+        }
+        note
+    }
+
+    fn header(&self) -> (String, String) {
+        (
+            format!(
+                "{} to {}",
+                self.period.start().format("%F"),
+                self.period.end().format("%F")
+            ),
+            format!("{} per hour", self.money(self.rate)),
+        )
+    }
+
+    pub fn render(&self, format: InvoiceFormat) -> Result<String> {
+        let mut buf = String::new();
+        let (period, rate) = self.header();
+
+        match format {
+            InvoiceFormat::Markdown => {
+                writeln!(buf, "# Invoice\n")?;
+                writeln!(buf, "- Period: {period}")?;
+                writeln!(buf, "- Rate: {rate}\n")?;
+                writeln!(buf, "| Date | Hours | Description | Amount |")?;
+                writeln!(buf, "| --- | ---: | --- | ---: |")?;
+                for line in &self.lines {
+                    writeln!(
+                        buf,
+                        "| {} | {} | {} | {} |",
+                        line.date.format("%F"),
+                        hours_minutes(line.duration),
+                        line.description.replace('|', "\\|"),
+                        line.amount
+                    )?;
+                }
+                writeln!(buf)?;
+                if let Some(note) = self.rounding_note() {
+                    writeln!(buf, "{note}\n")?;
+                }
+                writeln!(buf, "**{}**", self.total_note())?;
+            }
+            InvoiceFormat::Text => {
+                writeln!(buf, "Invoice for {period}")?;
+                writeln!(buf, "Rate: {rate}\n")?;
+                writeln!(
+                    buf,
+                    "{:<10}  {:>6}  {:>10}  Description",
+                    "Date", "Hours", "Amount"
+                )?;
+                for line in &self.lines {
+                    let row = format!(
+                        "{:<10}  {:>6}  {:>10}  {}",
+                        line.date.format("%F"),
+                        hours_minutes(line.duration),
+                        line.amount.to_string(),
+                        line.description
+                    );
+                    writeln!(buf, "{}", row.trim_end())?;
+                }
+                writeln!(buf)?;
+                if let Some(note) = self.rounding_note() {
+                    writeln!(buf, "{note}")?;
+                }
+                writeln!(buf, "{}", self.total_note())?;
+            }
+            InvoiceFormat::Html => {
+                writeln!(buf, "<!DOCTYPE html>")?;
+                writeln!(buf, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+                writeln!(buf, "<title>Invoice for {}</title>", escape_html(&period))?;
+                writeln!(buf, "</head>\n<body>")?;
+                writeln!(buf, "<h1>Invoice</h1>")?;
+                writeln!(buf, "<p>Period: {}<br>", escape_html(&period))?;
+                writeln!(buf, "Rate: {}</p>", escape_html(&rate))?;
+                writeln!(buf, "<table>")?;
+                writeln!(
+                    buf,
+                    "<tr><th>Date</th><th>Hours</th><th>Description</th><th>Amount</th></tr>"
+                )?;
+                for line in &self.lines {
+                    writeln!(
+                        buf,
+                        "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                        line.date.format("%F"),
+                        hours_minutes(line.duration),
+                        escape_html(&line.description),
+                        line.amount
+                    )?;
+                }
+                writeln!(buf, "</table>")?;
+                if let Some(note) = self.rounding_note() {
+                    writeln!(buf, "<p>{}</p>", escape_html(&note))?;
+                }
+                writeln!(
+                    buf,
+                    "<p><strong>{}</strong></p>",
+                    escape_html(&self.total_note())
+                )?;
+                writeln!(buf, "</body>\n</html>")?;
+            }
+        }
+
+        Ok(buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{FixedOffset, NaiveDate};
+    use pretty_assertions::assert_eq;
+
+    use super::build;
+    use crate::{
+        app::{
+            cli::{InvoiceFormat, RoundPer},
+            config::Config,
+            context::Context,
+        },
+        record::Record,
+    };
+
+    const RECORD_STR: &str = "2024-01-01T09:00:00+00:00 Design | review
+2024-01-01T10:20:00+00:00 Sent notes
+
+2024-01-02T23:00:00+00:00
+2024-01-03T01:07:00+00:00 Deploy
+
+2024-01-03T09:00:00+00:00
+";
+
+    fn invoice(round: Option<(&str, RoundPer)>, format: InvoiceFormat) -> String {
+        let ctx = Context::init(FixedOffset::east_opt(0).unwrap(), true, Config::default());
+        let record = Record::try_from(RECORD_STR).unwrap();
+        let date = |day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+        let round = round.map(|(rule, per)| (rule.parse().unwrap(), per));
+
+        build(
+            &ctx,
+            &record,
+            date(1)..=date(2),
+            "120".parse().unwrap(),
+            Some("NZD".to_owned()),
+            round,
+        )
+        .unwrap()
+        .render(format)
+        .unwrap()
+    }
+
+    #[test]
+    fn markdown_rounded_per_session() {
+        assert_eq!(
+            "# Invoice
+
+- Period: 2024-01-01 to 2024-01-02
+- Rate: 120.00 NZD per hour
+
+| Date | Hours | Description | Amount |
+| --- | ---: | --- | ---: |
+| 2024-01-01 | 1:15 | Design \\| review; Sent notes | 150.00 |
+| 2024-01-02 | 1:00 | Deploy | 120.00 |
+
+Rounded to the nearest 15m for each session
+
+**Total: 2:15 hours, 270.00 NZD**
+",
+            invoice(
+                Some(("nearest:15m", RoundPer::Session)),
+                InvoiceFormat::Markdown
+            )
+        );
+    }
+
+    #[test]
+    fn text_rounded_total() {
+        assert_eq!(
+            "Invoice for 2024-01-01 to 2024-01-02
+Rate: 120.00 NZD per hour
+
+Date         Hours      Amount  Description
+2024-01-01    1:20      160.00  Design | review; Sent notes
+2024-01-02    1:00      120.00  Deploy
+
+Rounded up to the next 1h for the total
+Total: 3:00 hours, 360.00 NZD (2:20 hours before rounding)
+",
+            invoice(Some(("up:1h", RoundPer::Total)), InvoiceFormat::Text)
+        );
+    }
+
+    #[test]
+    fn html_escapes_comments() {
+        let html = invoice(None, InvoiceFormat::Html);
+        assert!(html.contains("<td>Design | review; Sent notes</td>"));
+        assert!(!html.contains("Rounded"));
+
+        let ctx = Context::init(FixedOffset::east_opt(0).unwrap(), true, Config::default());
+        let record = Record::try_from(
+            "2024-01-01T09:00:00+00:00 <b>&</b>
+2024-01-01T10:00:00+00:00
+",
+        )
+        .unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let html = build(&ctx, &record, date..=date, "1".parse().unwrap(), None, None)
+            .unwrap()
+            .render(InvoiceFormat::Html)
+            .unwrap();
+        assert!(html.contains("<td>&lt;b&gt;&amp;&lt;/b&gt;</td>"));
+    }
+}
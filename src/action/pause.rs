@@ -0,0 +1,39 @@
+use chrono::{DateTime, Utc};
+
+use crate::{
+    error::Result,
+    record::Record,
+    string::assert_no_newlines,
+    time::{human_readable_duration, local_display, ContextTimeZone},
+};
+
+pub fn run<Tz: ContextTimeZone>(
+    record: &mut Record<Utc>,
+    comment: Option<String>,
+    at: DateTime<Tz>,
+) -> Result<()> {
+    let comment = comment.map(assert_no_newlines).transpose()?;
+
+    let timezone = at.timezone();
+    let break_time = record.take_break(comment, at)?;
+
+    println!(
+        "Taking a break on {}",
+        local_display(&break_time, &timezone)
+    );
+
+    Ok(())
+}
+
+pub fn resume<Tz: ContextTimeZone>(record: &mut Record<Utc>, at: DateTime<Tz>) -> Result<()> {
+    let timezone = at.timezone();
+    let (resume_time, length) = record.resume(at)?;
+
+    println!(
+        "Resuming on {} after a break of {}",
+        local_display(&resume_time, &timezone),
+        human_readable_duration(&length)?
+    );
+
+    Ok(())
+}
@@ -0,0 +1,330 @@
+use std::ops::RangeInclusive;
+
+use chrono::{Datelike, Duration, NaiveDate};
+
+use crate::{
+    app::{cli::GroupBy, context::Context},
+    error::{self, Result},
+    record::Record,
+    time::{human_readable_duration, ContextTimeZone},
+};
+
+fn group_label(day: NaiveDate, group_by: GroupBy) -> String {
+    match group_by {
+        GroupBy::Day => day.format("%F %a").to_string(),
+        GroupBy::Week => day.format("%G-W%V").to_string(),
+        GroupBy::Month => day.format("%Y-%m").to_string(),
+    }
+}
+
+fn same_group(a: NaiveDate, b: NaiveDate, group_by: GroupBy) -> bool {
+    match group_by {
+        GroupBy::Day => a == b,
+        GroupBy::Week => a.iso_week() == b.iso_week(),
+        GroupBy::Month => a.year() == b.year() && a.month() == b.month(),
+    }
+}
+
+/// The time worked in one group of days, labelled like "2024-W05"
+pub struct Group {
+    pub label: String,
+    pub worked: Duration,
+    pub break_time: Duration,
+}
+
+/// A day with less break time than `breaks.rules` required
+pub struct ShortDay {
+    pub date: NaiveDate,
+    pub shortfall: Duration,
+    pub required: Duration,
+}
+
+pub struct Report {
+    pub groups: Vec<Group>,
+    pub total: Duration,
+    pub total_break_time: Duration,
+    pub worked_days: usize,
+    /// `None` without any `breaks.rules` to check
+    pub short_days: Option<Vec<ShortDay>>,
+}
+
+impl Report {
+    /// Time worked per day with any time worked
+    pub fn average(&self) -> Option<Duration> {
+        i32::try_from(self.worked_days)
+            .ok()
+            .filter(|worked_days| *worked_days > 0)
+            .map(|worked_days| self.total / worked_days)
+    }
+}
+
+/// Time worked and on breaks for every day in `range`, totalled by `group_by`
+pub fn build<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    record: &Record<Tz>,
+    range: RangeInclusive<NaiveDate>,
+    group_by: GroupBy,
+) -> Result<Report> {
+    let range_start = *range.start();
+    let range_end = *range.end();
+    if range_end < range_start {
+        return Err(error::Main::RangeStartPosition);
+    }
+
+    let days = range_start
+        .iter_days()
+        .take_while(|d| d <= &range_end)
+        .map(|day| {
+            let break_time: Duration = record
+                .clone()
+                .days_break_ranges(ctx, day)?
+                .into_iter()
+                .sum();
+            Ok((day, record.clone().days_time(ctx, day)?, break_time))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut groups: Vec<(NaiveDate, Duration, Duration)> = vec![];
+    for (day, duration, break_time) in &days {
+        match groups.last_mut() {
+            Some((group_day, group_duration, group_break_time))
+                if same_group(*group_day, *day, group_by) =>
+            {
+                *group_duration += *duration;
+                *group_break_time += *break_time;
+            }
+            _ => groups.push((*day, *duration, *break_time)),
+        }
+    }
+
+    let short_days = if ctx.config.breaks.rules.is_empty() {
+        None
+    } else {
+        let mut short_days = vec![];
+        for (day, duration, _) in &days {
+            let rested = record.clone().days_rest(ctx, *day)?;
+            if let Some(shortfall) = ctx.config.breaks.shortfall(*duration, rested) {
+                short_days.push(ShortDay {
+                    date: *day,
+                    shortfall,
+                    required: ctx.config.breaks.required(*duration),
+                });
+            }
+        }
+        Some(short_days)
+    };
+
+    Ok(Report {
+        groups: groups
+            .into_iter()
+            .map(|(day, worked, break_time)| Group {
+                label: group_label(day, group_by),
+                worked,
+                break_time,
+            })
+            .collect(),
+        total: days.iter().map(|(_, duration, _)| *duration).sum(),
+        total_break_time: days.iter().map(|(_, _, break_time)| *break_time).sum(),
+        worked_days: days
+            .iter()
+            .filter(|(_, duration, _)| !duration.is_zero())
+            .count(),
+        short_days,
+    })
+}
+
+pub fn run<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    record: &Record<Tz>,
+    range: RangeInclusive<NaiveDate>,
+    group_by: GroupBy,
+) -> Result<()> {
+    let report = build(ctx, record, range.clone(), group_by)?;
+
+    println!(
+        "Report for {} to {}",
+        range.start().format("%F"),
+        range.end().format("%F")
+    );
+    for group in &report.groups {
+        let breaks = if group.break_time.is_zero() {
+            String::new()
+        } else {
+            format!(
+                " ({} of breaks)",
+                human_readable_duration(&group.break_time)?
+            )
+        };
+        println!(
+            "{:<14} {}{breaks}",
+            group.label,
+            human_readable_duration(&group.worked)?
+        );
+    }
+
+    println!("Total time: {}", human_readable_duration(&report.total)?);
+    if !report.total_break_time.is_zero() {
+        println!(
+            "Total break time: {}",
+            human_readable_duration(&report.total_break_time)?
+        );
+    }
+    println!("# of work days: {}", report.worked_days);
+    if let Some(average) = report.average() {
+        println!(
+            "Average work day time: {}",
+            human_readable_duration(&average)?
+        );
+    }
+
+    match report.short_days {
+        None => {}
+        Some(short_days) if short_days.is_empty() => {
+            println!("Required breaks: met on every day");
+        }
+        Some(short_days) => {
+            println!("Days short of the required breaks:");
+            for day in short_days {
+                println!(
+                    "  {} {} short of {}",
+                    day.date.format("%F %a"),
+                    human_readable_duration(&day.shortfall)?,
+                    human_readable_duration(&day.required)?
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, FixedOffset, NaiveDate};
+    use pretty_assertions::assert_eq;
+
+    use super::build;
+    use crate::{
+        app::{cli::GroupBy, config::Config, context::Context},
+        record::Record,
+    };
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn weeks_across_a_year() {
+        let ctx = Context::init(FixedOffset::east_opt(0).unwrap(), true, Config::default());
+        let record = Record::try_from(
+            "2024-12-29T09:00:00+00:00
+2024-12-29T10:00:00+00:00
+
+2024-12-31T09:00:00+00:00
+2024-12-31T11:00:00+00:00
+
+2025-01-01T09:00:00+00:00
+2025-01-01T12:00:00+00:00
+",
+        )
+        .unwrap();
+
+        let report = build(
+            &ctx,
+            &record,
+            date(2024, 12, 28)..=date(2025, 1, 1),
+            GroupBy::Week,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                ("2024-W52".to_owned(), Duration::hours(1)),
+                ("2025-W01".to_owned(), Duration::hours(5)),
+            ],
+            report
+                .groups
+                .iter()
+                .map(|group| (group.label.clone(), group.worked))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Duration::hours(6), report.total);
+        assert_eq!(3, report.worked_days);
+        assert_eq!(Some(Duration::hours(2)), report.average());
+        assert!(report.short_days.is_none());
+    }
+
+    #[test]
+    fn month_of_weeks_with_breaks() {
+        let config: Config = "[breaks]
+rules = [{ after = \"6h\", minimum = \"30m\" }]
+"
+        .parse()
+        .unwrap();
+        let ctx = Context::init(FixedOffset::east_opt(0).unwrap(), true, config);
+        let record = Record::try_from(
+            "2024-01-31T09:00:00+00:00
+2024-01-31T10:00:00+00:00
+
+2024-02-01T09:00:00+00:00
+2024-02-01T16:00:00+00:00
+@break 2024-02-01T12:00:00+00:00 2024-02-01T12:30:00+00:00
+
+2024-02-02T09:00:00+00:00
+2024-02-02T16:00:00+00:00
+
+2024-02-29T09:00:00+00:00
+2024-02-29T11:00:00+00:00
+
+2024-03-01T09:00:00+00:00
+2024-03-01T10:00:00+00:00
+",
+        )
+        .unwrap();
+
+        // As `report --month 2024-02` does, whose first and last weeks start in January and end in March
+        let report = build(
+            &ctx,
+            &record,
+            date(2024, 2, 1)..=date(2024, 2, 29),
+            GroupBy::Week,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                (
+                    "2024-W05".to_owned(),
+                    Duration::minutes(13 * 60 + 30),
+                    Duration::minutes(30)
+                ),
+                ("2024-W06".to_owned(), Duration::zero(), Duration::zero()),
+                ("2024-W07".to_owned(), Duration::zero(), Duration::zero()),
+                ("2024-W08".to_owned(), Duration::zero(), Duration::zero()),
+                ("2024-W09".to_owned(), Duration::hours(2), Duration::zero()),
+            ],
+            report
+                .groups
+                .iter()
+                .map(|group| (group.label.clone(), group.worked, group.break_time))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Duration::minutes(15 * 60 + 30), report.total);
+        assert_eq!(Duration::minutes(30), report.total_break_time);
+        assert_eq!(3, report.worked_days);
+        assert_eq!(Some(Duration::minutes(5 * 60 + 10)), report.average());
+
+        let short_days = report.short_days.unwrap();
+        assert_eq!(
+            vec![(
+                date(2024, 2, 2),
+                Duration::minutes(30),
+                Duration::minutes(30)
+            )],
+            short_days
+                .iter()
+                .map(|day| (day.date, day.shortfall, day.required))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use chrono::Utc;
+use dialoguer::Confirm;
+
+use crate::{
+    app::context::Context,
+    error::Result,
+    record::{display::describe_entry, Entry, Record, Storage},
+    time::{local_display, ContextTimeZone},
+};
+
+/// Entries that only exist in one of two records, where a changed comment, label or break counts as a different entry
+pub struct Diff<'a> {
+    pub added: Vec<&'a Entry<Utc>>,
+    pub removed: Vec<&'a Entry<Utc>>,
+}
+
+pub fn diff<'a>(current: &'a Record<Utc>, restored: &'a Record<Utc>) -> Diff<'a> {
+    let only_in = |a: &'a Record<Utc>, b: &'a Record<Utc>| {
+        a.get_entries()
+            .iter()
+            .filter(|entry| !b.get_entries().contains(entry))
+            .collect::<Vec<_>>()
+    };
+
+    Diff {
+        added: only_in(restored, current),
+        removed: only_in(current, restored),
+    }
+}
+
+/// Shows what restoring the backup with `id` would change, returning the backup if the user confirms
+pub fn run<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    storage: &Storage,
+    record: &Record<Utc>,
+    id: &str,
+) -> Result<Option<Record<Utc>>> {
+    let backup = storage.load_backup(id)?;
+    let Diff { added, removed } = diff(record, &backup);
+
+    for entry in &added {
+        println!("+ {}", describe_entry(entry, &ctx.timezone)?);
+    }
+    for entry in &removed {
+        println!("- {}", describe_entry(entry, &ctx.timezone)?);
+    }
+
+    let current_check_in = record.get_current_session().map(|s| s.check_in);
+    let restored_check_in = backup.get_current_session().map(|s| s.check_in);
+    if current_check_in != restored_check_in {
+        match restored_check_in {
+            Some(check_in) => println!(
+                "Will be clocked in since {}",
+                local_display(&check_in, &ctx.timezone)
+            ),
+            None => println!("Will be clocked out"),
+        }
+    } else if added.is_empty() && removed.is_empty() {
+        println!("The backup has the same sessions as the record.");
+    }
+
+    if !Confirm::new()
+        .with_prompt(format!(
+            "Replace the record with the backup from {id}? {} entries added, {} removed",
+            added.len(),
+            removed.len()
+        ))
+        .interact()?
+    {
+        return Ok(None);
+    }
+
+    Ok(Some(backup))
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use pretty_assertions::assert_eq;
+
+    use super::diff;
+    use crate::record::Record;
+
+    fn record(source: &str) -> Record<Utc> {
+        Record::try_from(source).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn added_and_removed() {
+        let current = record(
+            "2023-01-01T00:00:00+00:00
+2023-01-01T01:00:00+00:00
+
+2023-01-01T02:00:00+00:00
+2023-01-01T03:00:00+00:00
+",
+        );
+        let restored = record(
+            "2023-01-01T00:00:00+00:00
+2023-01-01T01:00:00+00:00
+
+2023-01-01T02:00:00+00:00
+2023-01-01T04:00:00+00:00
+
+2023-01-01T05:00:00+00:00
+2023-01-01T06:00:00+00:00
+",
+        );
+        let relabelled = record(
+            "2023-01-01T00:00:00+00:00 Planning
+2023-01-01T01:00:00+00:00
+@project acme
+
+2023-01-01T02:00:00+00:00
+2023-01-01T03:00:00+00:00
+",
+        );
+
+        let diff = diff(&current, &restored);
+        let check_ins = |entries: &[&crate::record::Entry<Utc>]| {
+            entries
+                .iter()
+                .map(|entry| entry.check_in.to_rfc3339())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec!["2023-01-01T02:00:00+00:00", "2023-01-01T05:00:00+00:00"],
+            check_ins(&diff.added)
+        );
+        assert_eq!(vec!["2023-01-01T02:00:00+00:00"], check_ins(&diff.removed));
+
+        // Same times, but a new comment and project
+        let diff = super::diff(&current, &relabelled);
+        assert_eq!(vec!["2023-01-01T00:00:00+00:00"], check_ins(&diff.added));
+        assert_eq!(vec!["2023-01-01T00:00:00+00:00"], check_ins(&diff.removed));
+    }
+}
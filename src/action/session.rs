@@ -0,0 +1,285 @@
+use std::ops::Range;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use dialoguer::Confirm;
+
+use crate::{
+    app::{
+        cli::{At, SessionAction},
+        context::Context,
+    },
+    error::{self, Result},
+    record::{display::describe_entry, Break, Entry, Record},
+    string::assert_no_newlines,
+    time::{local_display, ContextTimeZone},
+};
+
+/// Resolves `at`, taking a time without a date to be on the day `entry` starts
+fn resolve_at<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    at: &At,
+    entry: &Entry<Utc>,
+) -> Result<DateTime<FixedOffset>> {
+    let day = entry.check_in.with_timezone(&ctx.timezone).date_naive();
+    Ok(at.resolve_on(ctx, day)?.fixed_offset())
+}
+
+/// `comment` if given, where an empty one removes the existing comment
+fn amend_comment(existing: Option<&String>, comment: Option<&String>) -> Result<Option<String>> {
+    match comment {
+        Some(comment) if comment.is_empty() => Ok(None),
+        Some(comment) => assert_no_newlines(comment.clone()).map(Some),
+        None => Ok(existing.cloned()),
+    }
+}
+
+fn list<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    record: &Record<Utc>,
+    day: Option<NaiveDate>,
+) -> Result<()> {
+    let mut previous_day = None;
+    let mut nth = 0;
+
+    for (index, entry) in record.get_entries().iter().enumerate() {
+        let entry_day = entry.check_in.with_timezone(&ctx.timezone).date_naive();
+        if previous_day == Some(entry_day) {
+            nth += 1;
+        } else {
+            previous_day = Some(entry_day);
+            nth = 1;
+        }
+
+        if day.is_none_or(|day| day == entry_day) {
+            println!(
+                "{:>4}  {entry_day}#{nth}  {}",
+                index + 1,
+                describe_entry(entry, &ctx.timezone)?
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Shows the sessions in `range` being replaced by `entries`, and replaces them if the user confirms
+fn change<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    record: &mut Record<Utc>,
+    range: Range<usize>,
+    entries: Vec<Entry<Utc>>,
+) -> Result<bool> {
+    let now = Utc::now();
+    for entry in &entries {
+        let check_out = entry.get_check_out()?;
+        if check_out > now {
+            return Err(error::Main::TimeInFuture(local_display(
+                &check_out,
+                &ctx.timezone,
+            )));
+        }
+    }
+
+    for entry in &record.get_entries()[range.clone()] {
+        println!("- {}", describe_entry(entry, &ctx.timezone)?);
+    }
+    for entry in &entries {
+        println!("+ {}", describe_entry(entry, &ctx.timezone)?);
+    }
+
+    let mut changed = record.clone();
+    changed.replace_entries(range, entries, &ctx.timezone)?;
+
+    if !Confirm::new()
+        .with_prompt("Apply these changes?")
+        .interact()?
+    {
+        return Ok(false);
+    }
+
+    *record = changed;
+
+    Ok(true)
+}
+
+/// `first` and `second` as one session, with the time between them as a break
+fn merge(first: &Entry<Utc>, second: &Entry<Utc>) -> Result<Entry<Utc>> {
+    let mut labels = first.labels.clone();
+    labels.tags.extend(second.labels.tags.iter().cloned());
+    let check_in = first.recorded_check_in();
+
+    // The comments either side of the gap are kept as the break's
+    let comments = [&first.out_comment, &second.in_comment]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>();
+    let gap_start = first.get_check_out()?;
+    let gap = (gap_start < second.check_in).then(|| Break {
+        start: gap_start,
+        end: Some(second.check_in),
+        comment: Some(comments.join(" / ")).filter(|comment| !comment.is_empty()),
+    });
+
+    let breaks = first
+        .breaks
+        .iter()
+        .cloned()
+        .chain(gap)
+        .chain(second.breaks.iter().cloned())
+        .map(|pause| pause.with_timezone(check_in.offset()))
+        .collect();
+
+    Ok(Entry::try_new(
+        check_in,
+        second.recorded_check_out()?,
+        first.in_comment.clone(),
+        second.out_comment.clone(),
+    )?
+    .with_labels(labels)
+    .with_breaks(breaks)?
+    .with_timezone(&Utc))
+}
+
+/// Returns whether the record was changed
+pub fn run<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    record: &mut Record<Utc>,
+    action: &SessionAction,
+) -> Result<bool> {
+    match action {
+        SessionAction::List { day } => {
+            list(ctx, record, day.as_ref().map(|day| day.0))?;
+            Ok(false)
+        }
+        SessionAction::Split { id, at } => {
+            let index = id.resolve(ctx, record)?;
+            let entry = &record.get_entries()[index];
+            let at = resolve_at(ctx, at, entry)?;
+            let check_in = entry.recorded_check_in();
+            let check_out = entry.recorded_check_out()?;
+            if at <= check_in || at >= check_out {
+                return Err(error::Main::SplitOutsideSession);
+            }
+
+            // A break spanning `at` is split along with the session
+            let breaks = entry
+                .breaks
+                .iter()
+                .cloned()
+                .map(|pause| pause.with_timezone(at.offset()));
+            let first_breaks = breaks
+                .clone()
+                .filter_map(|pause| pause.ended_by(&at))
+                .collect();
+            let second_breaks = breaks
+                .filter(|pause| pause.end_or(&at) > at)
+                .map(|pause| Break {
+                    start: pause.start.max(at),
+                    ..pause
+                })
+                .collect();
+
+            let first = Entry::try_new(check_in, at, entry.in_comment.clone(), None)?
+                .with_labels(entry.labels.clone())
+                .with_breaks(first_breaks)?
+                .with_timezone(&Utc);
+            let second = Entry::try_new(at, check_out, None, entry.out_comment.clone())?
+                .with_labels(entry.labels.clone())
+                .with_breaks(second_breaks)?
+                .with_timezone(&Utc);
+
+            change(ctx, record, index..index + 1, vec![first, second])
+        }
+        SessionAction::Merge { first, second } => {
+            let first = first.resolve(ctx, record)?;
+            let second = second.resolve(ctx, record)?;
+            let (first, second) = (first.min(second), first.max(second));
+            if second != first + 1 {
+                return Err(error::Main::SessionsNotConsecutive);
+            }
+
+            let entries = record.get_entries();
+            let merged = merge(&entries[first], &entries[second])?;
+
+            change(ctx, record, first..second + 1, vec![merged])
+        }
+        SessionAction::Amend {
+            id,
+            start,
+            end,
+            in_comment,
+            out_comment,
+        } => {
+            let index = id.resolve(ctx, record)?;
+            let entry = &record.get_entries()[index];
+
+            let check_in = match start {
+                Some(start) => resolve_at(ctx, start, entry)?,
+                None => entry.recorded_check_in(),
+            };
+            let check_out = match end {
+                Some(end) => resolve_at(ctx, end, entry)?,
+                None => entry.recorded_check_out()?,
+            };
+            let amended = Entry::try_new(
+                check_in,
+                check_out,
+                amend_comment(entry.in_comment.as_ref(), in_comment.as_ref())?,
+                amend_comment(entry.out_comment.as_ref(), out_comment.as_ref())?,
+            )?
+            .with_labels(entry.labels.clone())
+            .with_breaks(
+                entry
+                    .breaks
+                    .iter()
+                    .cloned()
+                    .map(|pause| pause.with_timezone(check_in.offset()))
+                    .collect(),
+            )?
+            .with_timezone(&Utc);
+
+            change(ctx, record, index..index + 1, vec![amended])
+        }
+        SessionAction::Delete { id } => {
+            let index = id.resolve(ctx, record)?;
+            change(ctx, record, index..index + 1, vec![])
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, Utc};
+    use pretty_assertions::assert_eq;
+
+    use super::merge;
+    use crate::record::Record;
+
+    #[test]
+    fn merge_keeps_gap_as_break() {
+        let record = Record::try_from(
+            "2023-01-01T09:00:00+01:00 Morning
+2023-01-01T12:00:00+01:00 Lunch
+
+2023-01-01T13:00:00+01:00 Back
+2023-01-01T17:00:00+01:00 Done
+",
+        )
+        .unwrap()
+        .with_timezone(&Utc);
+        let entries = record.get_entries();
+
+        let merged = merge(&entries[0], &entries[1]).unwrap();
+        // The hour between the sessions isn't counted as work
+        assert_eq!(Duration::hours(8), merged.get_work_duration());
+        assert_eq!(Duration::hours(1), merged.get_break_duration());
+        assert_eq!(
+            Duration::hours(7),
+            merged.get_work_duration() - merged.get_break_duration()
+        );
+        assert_eq!(Some("Lunch / Back"), merged.breaks[0].comment.as_deref());
+        assert_eq!(Some("Morning"), merged.in_comment.as_deref());
+        assert_eq!(Some("Done"), merged.out_comment.as_deref());
+    }
+}
@@ -1,4 +1,4 @@
-use chrono::NaiveDate;
+use chrono::{Duration, NaiveDate};
 
 use crate::{
     app::context,
@@ -7,29 +7,97 @@ use crate::{
     time::{human_readable_duration, ContextTimeZone},
 };
 
+/// Prints the break time taken on `day`, and whether it meets the required breaks, if there's anything to say
+fn print_breaks<Tz: ContextTimeZone>(
+    ctx: &context::Context<Tz>,
+    record: &Record<Tz>,
+    day: NaiveDate,
+    label: &str,
+) -> Result<()> {
+    let break_time: Duration = record
+        .clone()
+        .days_break_ranges(ctx, day)?
+        .into_iter()
+        .sum();
+    if !break_time.is_zero() {
+        println!(
+            "Total break time {label}: {}",
+            human_readable_duration(&break_time)?
+        );
+    }
+
+    let worked = record.clone().days_time(ctx, day)?;
+    let required = ctx.config.breaks.required(worked);
+    if !required.is_zero() {
+        let rested = record.clone().days_rest(ctx, day)?;
+        match ctx.config.breaks.shortfall(worked, rested) {
+            Some(shortfall) => println!(
+                "Required breaks {label}: {} short of {}",
+                human_readable_duration(&shortfall)?,
+                human_readable_duration(&required)?
+            ),
+            None => println!(
+                "Required breaks {label}: met ({})",
+                human_readable_duration(&required)?
+            ),
+        }
+    }
+
+    Ok(())
+}
+
 pub fn run<Tz: ContextTimeZone>(
     ctx: &context::Context<Tz>,
     record: Record<Tz>,
     date: Option<NaiveDate>,
 ) -> Result<()> {
+    let policy = &ctx.config.rounding;
+
     if let Some(date) = date {
-        let record_duration = record.days_time(ctx, date)?;
+        let record_duration = policy.tally(&record.clone().days_ranges(ctx, date)?);
         println!(
             "Total time for given day: {}",
-            human_readable_duration(&record_duration)?
+            record_duration.display(human_readable_duration)?
         );
+        print_breaks(ctx, &record, date, "for given day")?;
     } else {
-        let record_duration = record.clone().total_time(ctx)?;
-        println!("Total time: {}", human_readable_duration(&record_duration)?);
-        let record_duration_today = record.clone().todays_time(ctx)?;
+        let record_duration = policy.tally(&record.clone().total_ranges(ctx)?);
+        println!(
+            "Total time: {}",
+            record_duration.display(human_readable_duration)?
+        );
+        let record_duration_today = policy.tally(&record.clone().todays_ranges(ctx)?);
         println!(
             "Total time today: {}",
-            human_readable_duration(&record_duration_today)?
+            record_duration_today.display(human_readable_duration)?
         );
+        print_breaks(ctx, &record, ctx.timezone.now().date_naive(), "today")?;
         if let Some(session_time) = record.current_session_time(ctx) {
             println!(
                 "Total time this session: {}",
-                human_readable_duration(&session_time)?
+                policy
+                    .session(session_time.abs())
+                    .display(human_readable_duration)?
+            );
+        }
+    }
+
+    let projects = record.projects();
+    if projects.iter().any(Option::is_some) {
+        println!("Total time by project:");
+        for project in projects {
+            let project_record = record.clone().for_project(project);
+            let project_ranges = if let Some(date) = date {
+                project_record.days_ranges(ctx, date)?
+            } else {
+                project_record.total_ranges(ctx)?
+            };
+            println!(
+                "  {}: {}",
+                project.unwrap_or("(no project)"),
+                policy
+                    .tally(&project_ranges)
+                    .display(human_readable_duration)?
             );
         }
     }
@@ -1,29 +1,229 @@
-use chrono::Utc;
+use std::process::ExitCode;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 
 use crate::{
+    app::{cli::StatusFormat, context::Context},
     error::Result,
     record::{Latest, Record},
-    time::Ago,
+    time::{compact_duration, Ago, ContextTimeZone},
 };
 
-pub fn run(record: &Record<Utc>) -> Result<()> {
-    match record.get_latest() {
-        Latest::Current(current_session, _) => {
-            let since = current_session.signed_duration_since(Utc::now());
-            let ago = Ago(since);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum State {
+    In,
+    Out,
+    Empty,
+}
 
-            println!("Currently clocked in ({ago})");
+impl State {
+    /// 0 when clocked in, so `punch_clock status && ...` reads naturally
+    fn exit_code(self) -> ExitCode {
+        match self {
+            State::In => ExitCode::SUCCESS,
+            State::Out => ExitCode::from(1),
+            State::Empty => ExitCode::from(2),
         }
-        Latest::Entry(last_entry) => {
-            let since = last_entry
-                .get_check_out()?
-                .signed_duration_since(Utc::now());
-            let ago = Ago(since);
+    }
 
-            println!("Currently clocked out ({ago})");
+    fn name(self) -> &'static str {
+        match self {
+            State::In => "in",
+            State::Out => "out",
+            State::Empty => "empty",
         }
-        Latest::None => println!("No clock in/out records have been created."),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Status {
+    state: State,
+    /// When the current session began, or the last one ended, in RFC 3339
+    since: Option<String>,
+    elapsed_seconds: Option<i64>,
+    comment: Option<String>,
+    project: Option<String>,
+    tags: Vec<String>,
+}
+
+impl Status {
+    fn new<Tz: ContextTimeZone>(
+        ctx: &Context<Tz>,
+        record: &Record<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<Self> {
+        let (state, since, comment, labels) = match record.get_latest() {
+            Latest::Current(session) => (
+                State::In,
+                Some(session.check_in),
+                session.in_comment.clone(),
+                Some(&session.labels),
+            ),
+            Latest::Entry(entry) => (
+                State::Out,
+                Some(entry.get_check_out()?),
+                entry.out_comment.clone(),
+                Some(&entry.labels),
+            ),
+            Latest::None => (State::Empty, None, None, None),
+        };
+
+        Ok(Self {
+            state,
+            since: since.map(|since| since.with_timezone(&ctx.timezone).to_rfc3339()),
+            elapsed_seconds: since.map(|since| (now - since).num_seconds()),
+            comment,
+            project: labels.and_then(|labels| labels.project.clone()),
+            tags: labels
+                .map(|labels| labels.tags.iter().cloned().collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    fn elapsed(&self) -> Option<chrono::Duration> {
+        self.elapsed_seconds.map(chrono::Duration::seconds)
+    }
+
+    /// The value of a template placeholder, which was checked to be known when the template was parsed
+    fn placeholder(&self, name: &str) -> String {
+        match name {
+            "state" => self.state.name().to_owned(),
+            "elapsed" => self
+                .elapsed()
+                .map(|elapsed| compact_duration(&elapsed))
+                .unwrap_or_default(),
+            "since" => self.since.clone().unwrap_or_default(),
+            "comment" => self.comment.clone().unwrap_or_default(),
+            "project" => self.project.clone().unwrap_or_default(),
+            "tags" => self.tags.join(" "),
+            _ => unreachable!("unknown placeholder {{{name}}}"),
+        }
+    }
+
+    fn render(&self, format: &StatusFormat) -> Result<String> {
+        let elapsed = self.elapsed().map(|elapsed| compact_duration(&elapsed));
+
+        Ok(match format {
+            StatusFormat::Text => match (self.state, self.elapsed()) {
+                (State::In, Some(elapsed)) => format!("Currently clocked in ({})", Ago(-elapsed)),
+                (State::Out, Some(elapsed)) => {
+                    format!("Currently clocked out ({})", Ago(-elapsed))
+                }
+                _ => "No clock in/out records have been created.".to_owned(),
+            },
+            StatusFormat::Short => match (self.state, elapsed) {
+                (State::In, Some(elapsed)) => format!("● {elapsed}"),
+                (State::Out, Some(elapsed)) => format!("○ {elapsed}"),
+                _ => "○".to_owned(),
+            },
+            StatusFormat::Json => serde_json::to_string(self)?,
+            // Scanned once, so braces in a value are never taken for placeholders
+            StatusFormat::Template(template) => {
+                let mut text = String::new();
+                let mut rest = template.as_str();
+                while let Some(open) = rest.find('{') {
+                    let close = open
+                        + rest[open..]
+                            .find('}')
+                            .expect("placeholders are checked to be closed when parsing");
+                    text.push_str(&rest[..open]);
+                    text.push_str(&self.placeholder(&rest[open + 1..close]));
+                    rest = &rest[close + 1..];
+                }
+                text.push_str(rest);
+                text
+            }
+        })
+    }
+}
+
+/// Prints the status, returning the exit code for it: 0 if clocked in, 1 if clocked out, or 2 if the record is empty
+pub fn run<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    record: &Record<Utc>,
+    format: &StatusFormat,
+) -> Result<ExitCode> {
+    let status = Status::new(ctx, record, Utc::now())?;
+    println!("{}", status.render(format)?);
+
+    Ok(status.state.exit_code())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{DateTime, FixedOffset, Utc};
+    use pretty_assertions::assert_eq;
+
+    use super::Status;
+    use crate::{
+        app::{cli::StatusFormat, config::Config, context::Context},
+        record::Record,
     };
 
-    Ok(())
+    fn render(record: &str, format: &str) -> String {
+        let ctx = Context::init(
+            FixedOffset::east_opt(13 * 3600).unwrap(),
+            true,
+            Config::default(),
+        );
+        let record = Record::try_from(record).unwrap().with_timezone(&Utc);
+        let now: DateTime<Utc> = "2024-01-01T02:13:30Z".parse().unwrap();
+        Status::new(&ctx, &record, now)
+            .unwrap()
+            .render(&format.parse::<StatusFormat>().unwrap())
+            .unwrap()
+    }
+
+    const CLOCKED_IN: &str = "2024-01-01T00:00:00+00:00 Fixing the build
+@project acme
+@tags ci urgent
+";
+
+    #[test]
+    fn formats() {
+        assert_eq!(
+            "Currently clocked in (2 hours, 13 minutes ago)",
+            render(CLOCKED_IN, "text")
+        );
+        assert_eq!("● 2h13m", render(CLOCKED_IN, "short"));
+        assert_eq!(
+            r#"{"state":"in","since":"2024-01-01T13:00:00+13:00","elapsed_seconds":8010,"comment":"Fixing the build","project":"acme","tags":["ci","urgent"]}"#,
+            render(CLOCKED_IN, "json")
+        );
+        assert_eq!(
+            "in 2h13m Fixing the build [acme: ci urgent]",
+            render(
+                CLOCKED_IN,
+                "{state} {elapsed} {comment} [{project}: {tags}]"
+            )
+        );
+        assert_eq!(
+            "○ 13m",
+            render(
+                "2024-01-01T00:00:00+00:00\n2024-01-01T02:00:00+00:00\n",
+                "short"
+            )
+        );
+        assert_eq!("empty ", render("", "{state} {elapsed}"));
+    }
+
+    #[test]
+    fn placeholders_in_values() {
+        assert_eq!(
+            "Fixing {project} and {tags} (acme)",
+            render(
+                "2024-01-01T00:00:00+00:00 Fixing {project} and {tags}\n@project acme\n",
+                "{comment} ({project})"
+            )
+        );
+    }
+
+    #[test]
+    fn invalid_templates() {
+        assert!("{state} {duration}".parse::<StatusFormat>().is_err());
+        assert!("{state".parse::<StatusFormat>().is_err());
+        assert!("long".parse::<StatusFormat>().is_err());
+    }
 }
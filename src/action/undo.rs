@@ -1,44 +1,56 @@
-use chrono::{Local, Utc};
+use chrono::{DateTime, Utc};
 use dialoguer::Confirm;
 
 use crate::{
+    app::context::Context,
     error::Result,
     record::{Latest, Record},
+    time::{local_display, ContextTimeZone},
 };
 
-pub fn run(record: &mut Record<Utc>) -> Result<()> {
+/// Returns the removed timestamp and its comment, if the user went through with it
+pub fn run<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    record: &mut Record<Utc>,
+) -> Result<Option<(DateTime<Utc>, Option<String>)>> {
     let Some(dt) = record.clone_last_datetime()? else {
         println!("Record is empty; nothing to undo.");
-        return Ok(());
+        return Ok(None);
     };
 
     if !Confirm::new()
         .with_prompt(format!(
             "Are you sure you want to remove the most recent timestamp at {}?",
-            dt.with_timezone(&Local).format("%c")
+            local_display(&dt, &ctx.timezone)
         ))
         .interact()?
     {
-        return Ok(());
+        return Ok(None);
     }
 
-    let (dt, _) = record.pop().expect("presence checked at start of function");
+    let (dt, comment) = record.pop().expect("presence checked at start of function");
     println!(
         "Removed latest timestamp from {}",
-        dt.with_timezone(&Local).format("%c")
+        local_display(&dt, &ctx.timezone)
     );
     match record.get_latest() {
         Latest::Entry(entry) => {
             let check_out = entry.get_check_out()?;
             println!(
                 "Now clocked out since {}",
-                check_out.with_timezone(&Local).format("%c")
+                local_display(&check_out, &ctx.timezone)
             );
         }
-        Latest::Current(current_session, _) => {
+        Latest::Current(current_session) => {
+            if let Some(open_break) = current_session.open_break() {
+                println!(
+                    "Now on a break since {}",
+                    local_display(&open_break.start, &ctx.timezone)
+                );
+            }
             println!(
                 "Now clocked in since {}",
-                current_session.with_timezone(&Local).format("%c")
+                local_display(&current_session.check_in, &ctx.timezone)
             );
         }
         Latest::None => {
@@ -46,5 +58,5 @@ pub fn run(record: &mut Record<Utc>) -> Result<()> {
         }
     }
 
-    Ok(())
+    Ok(Some((dt, comment)))
 }
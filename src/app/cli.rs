@@ -1,8 +1,63 @@
+mod at;
 mod day;
+mod month;
+mod session_id;
+mod week;
 
-use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use chrono::{DateTime, Duration, FixedOffset};
+
+use crate::{
+    app::context::Context,
+    error::Result,
+    money::Amount,
+    record::Filter,
+    time::{rounding::Rounding, ContextTimeZone},
+};
+
+pub use at::At;
 pub use day::Day;
+pub use month::Month;
+pub use session_id::SessionId;
+pub use week::Week;
+
+/// Paths are resolved before `punch_clock` moves to the project root
+fn absolute_path(value: &str) -> std::io::Result<PathBuf> {
+    std::path::absolute(value)
+}
+
+fn duration(value: &str) -> Result<Duration, String> {
+    crate::time::parse_duration(value)
+        .ok_or_else(|| "expected a duration like 20m or 1h30m".to_owned())
+}
+
+fn offset(value: &str) -> Result<FixedOffset, String> {
+    crate::time::parse_offset(value)
+        .ok_or_else(|| "expected an offset like 13, -3 or +05:30".to_owned())
+}
+
+fn invalid_value(cmd: &clap::Command, arg: Option<&clap::Arg>, val: String) -> clap::Error {
+    let mut err = clap::Error::new(clap::error::ErrorKind::ValueValidation).with_cmd(cmd);
+
+    if let Some(arg) = arg {
+        err.insert(
+            clap::error::ContextKind::InvalidArg,
+            clap::error::ContextValue::String(arg.to_string()),
+        );
+        err.insert(
+            clap::error::ContextKind::InvalidValue,
+            clap::error::ContextValue::String(val),
+        );
+    }
+
+    // TODO: Bother clap-rs devs about this
+    // err.inner.set_source()
+
+    err
+}
 
 #[derive(Parser)]
 #[command(author, version = crate::GIT_REVISION, long_version = crate::LONG_VERSION, about)]
@@ -13,15 +68,65 @@ pub struct Base {
     #[arg(long)]
     /// Create a `.punch_clock` directory in the current working directory
     pub init: bool,
-    /// Override `punch_clock`'s current UTC offset
-    #[arg(short, long)]
-    pub offset: Option<i32>,
+    /// Override `punch_clock`'s current UTC offset, in hours or like +05:30 (also configurable as `offset`)
+    #[arg(short, long, value_parser = offset, allow_hyphen_values = true, conflicts_with = "timezone")]
+    pub offset: Option<FixedOffset>,
+    /// Use a timezone from the tz database, like Pacific/Auckland (also configurable as `timezone`)
+    #[arg(long = "tz")]
+    pub timezone: Option<chrono_tz::Tz>,
     /// Run a command without triggering any hooks
     #[arg(long, default_value_t = false)]
     pub skip_hooks: bool,
 }
 
-#[derive(Clone, ValueEnum)]
+/// Only include sessions with these labels
+#[derive(Args)]
+pub struct LabelFilter {
+    /// Only include sessions for this project
+    #[arg(long)]
+    pub project: Option<String>,
+    /// Only include sessions with this tag (can be repeated)
+    #[arg(long = "tag")]
+    pub tags: Vec<String>,
+}
+
+impl LabelFilter {
+    pub fn to_filter(&self) -> Filter {
+        Filter {
+            project: self.project.clone(),
+            tags: self.tags.clone(),
+        }
+    }
+}
+
+/// When to clock in or out, instead of now
+#[derive(Args)]
+pub struct When {
+    /// A time today (HH:MM), or a date and time (YYYY-MM-DD HH:MM), in the current timezone
+    #[arg(long, conflicts_with = "ago")]
+    pub at: Option<At>,
+    /// How long ago (e.g. 20m, 1h30m)
+    #[arg(long, value_parser = duration)]
+    pub ago: Option<Duration>,
+}
+
+impl When {
+    /// The time to punch at, in the current timezone
+    pub fn resolve<Tz: ContextTimeZone>(&self, ctx: &Context<Tz>) -> Result<DateTime<Tz>> {
+        if let Some(at) = &self.at {
+            return at.resolve(ctx);
+        }
+
+        let now = ctx.timezone.now();
+        Ok(match self.ago {
+            Some(ago) => now - ago,
+            None => now,
+        })
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum DayResolution {
     Hour = 1,
     HalfHour = 2,
@@ -48,28 +153,219 @@ impl DayResolution {
     }
 }
 
+/// What a rounding rule is applied to
+#[derive(Debug, Clone, Copy, ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RoundPer {
+    Session,
+    Total,
+}
+
+/// How `status` is printed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatusFormat {
+    /// A sentence, like "Currently clocked in (2 hours, 13 minutes ago)"
+    Text,
+    /// A symbol and the time elapsed, like "● 2h13m"
+    Short,
+    Json,
+    /// Text with placeholders, like "{state} {elapsed} {comment}"
+    Template(String),
+}
+
+impl StatusFormat {
+    pub const PLACEHOLDERS: [&'static str; 6] =
+        ["state", "elapsed", "since", "comment", "project", "tags"];
+}
+
+impl std::str::FromStr for StatusFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "text" => return Ok(Self::Text),
+            "short" => return Ok(Self::Short),
+            "json" => return Ok(Self::Json),
+            _ => {}
+        }
+
+        if !value.contains('{') {
+            return Err(format!(
+                "expected \"text\", \"short\", \"json\" or a template like \"{{state}} {{elapsed}}\", got {value:?}"
+            ));
+        }
+        let mut rest = value;
+        while let Some(open) = rest.find('{') {
+            let Some(close) = rest[open..].find('}') else {
+                return Err(format!("unclosed placeholder in {value:?}"));
+            };
+            let placeholder = &rest[open + 1..open + close];
+            if !Self::PLACEHOLDERS.contains(&placeholder) {
+                return Err(format!(
+                    "unknown placeholder {{{placeholder}}}, expected one of {{{}}}",
+                    Self::PLACEHOLDERS.join("}, {")
+                ));
+            }
+            rest = &rest[open + close + 1..];
+        }
+
+        Ok(Self::Template(value.to_owned()))
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum InvoiceFormat {
+    Markdown,
+    Text,
+    Html,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum CalendarOutput {
+    /// Block characters, for a terminal
+    Terminal,
+    /// A standalone HTML page
+    Html,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum DayOutput {
+    /// Block characters, for a terminal
+    Terminal,
+    /// An SVG image
+    Svg,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum GroupBy {
+    Day,
+    Week,
+    Month,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    /// Newline-delimited JSON, one session per line
+    Ndjson,
+    /// iCalendar, one event per session
+    Ics,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ImportFormat {
+    /// The JSON output of `timew export`
+    Timewarrior,
+    /// The JSON output of `watson log --json`, or Watson's `frames` file
+    Watson,
+    /// A Toggl Track detailed report CSV, read in the current timezone
+    TogglCsv,
+    /// An iCalendar file, with floating times read in the current timezone
+    Ics,
+}
+
+#[derive(Subcommand)]
+pub enum BackupsAction {
+    /// List the snapshots of the record, oldest first
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum SessionAction {
+    /// List finished sessions with their ids
+    List {
+        /// Only sessions starting on this day (YYYY-MM-DD)
+        day: Option<day::Day>,
+    },
+    /// Split a session in two
+    Split {
+        id: SessionId,
+        /// Where to split, a time (HH:MM) on the session's day or a date and time (YYYY-MM-DD HH:MM)
+        #[arg(long)]
+        at: At,
+    },
+    /// Merge two consecutive sessions into one, keeping the labels of the first and the time between them as a break
+    Merge { first: SessionId, second: SessionId },
+    /// Change the times or comments of a session
+    Amend {
+        id: SessionId,
+        /// A time (HH:MM) on the session's day, or a date and time (YYYY-MM-DD HH:MM)
+        #[arg(long)]
+        start: Option<At>,
+        /// A time (HH:MM) on the session's day, or a date and time (YYYY-MM-DD HH:MM)
+        #[arg(long)]
+        end: Option<At>,
+        /// An empty comment removes it
+        #[arg(long)]
+        in_comment: Option<String>,
+        /// An empty comment removes it
+        #[arg(long)]
+        out_comment: Option<String>,
+    },
+    /// Remove a session from the record
+    Delete { id: SessionId },
+}
+
 #[derive(Subcommand)]
 pub enum Action {
     /// Start a session
     In {
         /// Provide a comment associated with the start of this session
         comment: Option<String>,
+        /// The project this session is for
+        #[arg(long)]
+        project: Option<String>,
+        /// Tag this session (can be repeated)
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        #[command(flatten)]
+        when: When,
     },
     /// End the current session
     Out {
         /// Provide a comment associated with the end of this session
         comment: Option<String>,
+        #[command(flatten)]
+        when: When,
+    },
+    /// Pause the current session, so the time until `resume` isn't counted as work
+    Break {
+        /// Provide a comment associated with this break
+        comment: Option<String>,
+        #[command(flatten)]
+        when: When,
+    },
+    /// End the current break
+    Resume {
+        #[command(flatten)]
+        when: When,
     },
-    /// Check whether you're currently in a session
-    Status,
+    /// Check whether you're currently in a session, exiting with 0 if clocked in, 1 if clocked out, or 2 if the record is empty (errors exit with 3)
+    Status {
+        /// "text", "short", "json", or a template using {state}, {elapsed}, {since}, {comment}, {project} and {tags}
+        #[arg(long, default_value = "text")]
+        format: StatusFormat,
+    },
+    /// Note that you're still working, so the current session isn't taken to be idle
+    Ping,
     /// Print the record, formatted
     Dump,
-    /// Open the record in your editor, in local time
-    Edit,
+    /// Open the record in your editor, with times in the offsets they were recorded in
+    Edit {
+        /// Only edit sessions starting on or after this day (YYYY-MM-DD)
+        #[arg(long)]
+        from: Option<day::Day>,
+        /// Only edit sessions starting on or before this day (YYYY-MM-DD)
+        #[arg(long)]
+        to: Option<day::Day>,
+    },
     /// See some stats about your work hours
     Stats {
         /// For a particular day (YYYY-MM-DD)
         day: Option<day::Day>,
+        #[command(flatten)]
+        filter: LabelFilter,
     },
     /// Print daily visualization of work hours (past 7 days by default)
     Calendar {
@@ -77,17 +373,151 @@ pub enum Action {
         from: Option<day::Day>,
         /// YYYY-MM-DD
         to: Option<day::Day>,
-        /// Set the character width of the calendar
-        #[arg(long, default_value_t = 48)]
-        width: usize,
+        /// Set the character width of the calendar [default: `calendar.width` from config, or 48]
+        #[arg(long)]
+        width: Option<usize>,
+        /// Show each session at the local time it was recorded in, and flag days where the offset changed
+        #[arg(long)]
+        as_recorded: bool,
+        #[arg(long, value_enum, default_value = "terminal")]
+        output: CalendarOutput,
+        #[command(flatten)]
+        filter: LabelFilter,
+    },
+    /// Print a timesheet of work hours with subtotals (this week by default)
+    Report {
+        /// An ISO week (YYYY-Www), grouped by day by default
+        #[arg(long, conflicts_with_all = ["month", "from", "to"])]
+        week: Option<Week>,
+        /// A month (YYYY-MM), grouped by week by default
+        #[arg(long, conflicts_with_all = ["from", "to"])]
+        month: Option<Month>,
+        /// YYYY-MM-DD
+        #[arg(long, requires = "to")]
+        from: Option<day::Day>,
+        /// YYYY-MM-DD
+        #[arg(long, requires = "from")]
+        to: Option<day::Day>,
+        /// How to group the subtotals
+        #[arg(long, value_enum)]
+        by: Option<GroupBy>,
+        #[command(flatten)]
+        filter: LabelFilter,
+    },
+    /// Compare time worked against the `[schedule]` in config, with a running overtime balance
+    Balance {
+        /// YYYY-MM-DD [default: `schedule.start` from config, or the first day in the record]
+        #[arg(long)]
+        from: Option<day::Day>,
+        /// YYYY-MM-DD [default: today]
+        #[arg(long)]
+        to: Option<day::Day>,
+        #[command(flatten)]
+        filter: LabelFilter,
+    },
+    /// Print an invoice with a line for each session
+    Invoice {
+        /// YYYY-MM-DD
+        #[arg(long)]
+        from: day::Day,
+        /// YYYY-MM-DD
+        #[arg(long)]
+        to: day::Day,
+        /// Hourly rate [default: `invoice.rate` from config]
+        #[arg(long)]
+        rate: Option<Amount>,
+        /// [default: `invoice.currency` from config]
+        #[arg(long)]
+        currency: Option<String>,
+        /// Round like "nearest:15m", "up:6m" or "down:1h" [default: `invoice.round` from config]
+        #[arg(long)]
+        round: Option<Rounding>,
+        /// [default: `invoice.round_per` from config, or session]
+        #[arg(long, value_enum)]
+        round_per: Option<RoundPer>,
+        #[arg(long, value_enum, default_value = "markdown")]
+        format: InvoiceFormat,
+        #[command(flatten)]
+        filter: LabelFilter,
+    },
+    /// Print sessions in a machine-readable format
+    Export {
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+        /// Only sessions ending on or after this day (YYYY-MM-DD)
+        #[arg(long)]
+        from: Option<day::Day>,
+        /// Only sessions starting on or before this day (YYYY-MM-DD)
+        #[arg(long)]
+        to: Option<day::Day>,
+        #[command(flatten)]
+        filter: LabelFilter,
+    },
+    /// Merge sessions exported from another time tracker into the record
+    Import {
+        /// The format of the file
+        #[arg(long = "from", visible_alias = "format", value_enum)]
+        format: ImportFormat,
+        #[arg(value_parser = absolute_path)]
+        file: PathBuf,
+        /// Print the merged record without saving it
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Remove the latest entry in the record
     Undo,
+    /// Insert a complete session into the record, anywhere in the past
+    Add {
+        /// A time today (HH:MM), or a date and time (YYYY-MM-DD HH:MM)
+        start: At,
+        /// A time (HH:MM) on the same day as the start, or a date and time (YYYY-MM-DD HH:MM)
+        end: At,
+        /// Comment associated with the start of the session
+        #[arg(long)]
+        in_comment: Option<String>,
+        /// Comment associated with the end of the session
+        #[arg(long)]
+        out_comment: Option<String>,
+        /// The project this session is for
+        #[arg(long)]
+        project: Option<String>,
+        /// Tag this session (can be repeated)
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// Add the session even if it overlaps others
+        #[arg(long)]
+        force: bool,
+    },
+    /// Change finished sessions, addressed by their position (see `session list`) or day (YYYY-MM-DD, or YYYY-MM-DD#n for the nth session that day)
+    Session {
+        #[command(subcommand)]
+        action: SessionAction,
+    },
+    /// Check the record for mistakes, reporting each one with its line number
+    Check,
+    /// Manage the snapshots of the record taken before every change
+    Backups {
+        #[command(subcommand)]
+        action: BackupsAction,
+    },
+    /// Replace the record with one of its snapshots
+    Restore {
+        /// The id of the snapshot, as shown by `backups list`
+        id: String,
+    },
     /// Print visualization of a day's work hours (today by default)
     Day {
         /// YYYY-MM-DD
         date: Option<day::Day>,
-        #[arg(short = 'r', long, value_enum, default_value_t = DayResolution::Hour)]
-        resolution: DayResolution,
+        /// [default: `day.resolution` from config, or hour]
+        #[arg(short = 'r', long, value_enum)]
+        resolution: Option<DayResolution>,
+        /// Show each session at the local time it was recorded in, and flag if the offset changed
+        #[arg(long)]
+        as_recorded: bool,
+        #[arg(long, value_enum, default_value = "terminal")]
+        output: DayOutput,
+        #[command(flatten)]
+        filter: LabelFilter,
     },
 }
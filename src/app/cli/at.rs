@@ -0,0 +1,85 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+
+use crate::{
+    app::context::Context,
+    error::{self, Result},
+    time::ContextTimeZone,
+};
+
+/// A time today, or a date and time, in the context timezone
+#[derive(Clone)]
+pub enum At {
+    Today(NaiveTime),
+    DateTime(NaiveDateTime),
+}
+
+impl At {
+    /// Parses `HH:MM`, `YYYY-MM-DD HH:MM` or `YYYY-MM-DD`, optionally with seconds
+    pub fn parse(value: &str) -> Option<Self> {
+        for format in ["%R", "%T"] {
+            if let Ok(time) = NaiveTime::parse_from_str(value, format) {
+                return Some(At::Today(time));
+            }
+        }
+
+        for format in ["%F %R", "%F %T", "%FT%R", "%FT%T"] {
+            if let Ok(datetime) = NaiveDateTime::parse_from_str(value, format) {
+                return Some(At::DateTime(datetime));
+            }
+        }
+
+        // A bare date means the start of that day
+        if let Ok(date) = NaiveDate::parse_from_str(value, "%F") {
+            return Some(At::DateTime(date.and_time(NaiveTime::MIN)));
+        }
+
+        None
+    }
+
+    pub fn resolve<Tz: ContextTimeZone>(&self, ctx: &Context<Tz>) -> Result<DateTime<Tz>> {
+        self.resolve_on(ctx, ctx.timezone.now().date_naive())
+    }
+
+    /// Like [`At::resolve`], but a time without a date is taken to be on `date`
+    pub fn resolve_on<Tz: ContextTimeZone>(
+        &self,
+        ctx: &Context<Tz>,
+        date: NaiveDate,
+    ) -> Result<DateTime<Tz>> {
+        let datetime = match self {
+            At::Today(time) => date.and_time(*time),
+            At::DateTime(datetime) => *datetime,
+        };
+
+        datetime
+            .and_local_timezone(ctx.timezone)
+            .single()
+            .ok_or_else(|| error::Main::InvalidLocalTime(datetime.to_string()))
+    }
+}
+
+impl clap::builder::ValueParserFactory for At {
+    type Parser = Parser;
+    fn value_parser() -> Self::Parser {
+        Parser
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Parser;
+
+impl clap::builder::TypedValueParser for Parser {
+    type Value = At;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        let inner = clap::builder::NonEmptyStringValueParser::new();
+        let val = inner.parse_ref(cmd, arg, value)?;
+
+        At::parse(&val).ok_or_else(|| super::invalid_value(cmd, arg, val))
+    }
+}
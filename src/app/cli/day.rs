@@ -29,22 +29,6 @@ impl clap::builder::TypedValueParser for Parser {
             return Ok(Day(date));
         }
 
-        let mut err = clap::Error::new(clap::error::ErrorKind::ValueValidation).with_cmd(cmd);
-
-        if let Some(arg) = arg {
-            err.insert(
-                clap::error::ContextKind::InvalidArg,
-                clap::error::ContextValue::String(arg.to_string()),
-            );
-            err.insert(
-                clap::error::ContextKind::InvalidValue,
-                clap::error::ContextValue::String(val),
-            );
-        }
-
-        // TODO: Bother clap-rs devs about this
-        // err.inner.set_source()
-
-        Err(err)
+        Err(super::invalid_value(cmd, arg, val))
     }
 }
@@ -0,0 +1,47 @@
+use chrono::{Months, NaiveDate};
+
+/// A calendar month, represented by its first day
+#[derive(Clone)]
+pub struct Month(pub NaiveDate);
+
+impl Month {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (year, month) = value.split_once('-')?;
+        let first = NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, 1)?;
+        Some(Month(first))
+    }
+
+    pub fn last_day(&self) -> Option<NaiveDate> {
+        self.0.checked_add_months(Months::new(1))?.pred_opt()
+    }
+}
+
+impl clap::builder::ValueParserFactory for Month {
+    type Parser = Parser;
+    fn value_parser() -> Self::Parser {
+        Parser
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Parser;
+
+impl clap::builder::TypedValueParser for Parser {
+    type Value = Month;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        let inner = clap::builder::NonEmptyStringValueParser::new();
+        let val = inner.parse_ref(cmd, arg, value)?;
+
+        if let Some(month) = Month::parse(&val) {
+            return Ok(month);
+        }
+
+        Err(super::invalid_value(cmd, arg, val))
+    }
+}
@@ -0,0 +1,109 @@
+use chrono::{NaiveDate, Utc};
+
+use crate::{
+    app::context::Context,
+    error::{self, Result},
+    record::Record,
+    time::ContextTimeZone,
+};
+
+/// A finished session, by its position in the record or by its day
+#[derive(Clone)]
+pub enum SessionId {
+    /// 1-based position among all finished sessions
+    Index(usize),
+    /// The only session on a day, or the nth one when given as `YYYY-MM-DD#n`
+    Day(NaiveDate, Option<usize>),
+}
+
+impl std::fmt::Display for SessionId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionId::Index(index) => write!(f, "{index}"),
+            SessionId::Day(date, None) => write!(f, "{date}"),
+            SessionId::Day(date, Some(nth)) => write!(f, "{date}#{nth}"),
+        }
+    }
+}
+
+impl SessionId {
+    /// The index of the session in [`Record::get_entries`]
+    pub fn resolve<Tz: ContextTimeZone>(
+        &self,
+        ctx: &Context<Tz>,
+        record: &Record<Utc>,
+    ) -> Result<usize> {
+        let not_found = || error::Main::NoSuchSession(self.to_string());
+
+        match *self {
+            SessionId::Index(index) => index
+                .checked_sub(1)
+                .filter(|&index| index < record.get_entries().len())
+                .ok_or_else(not_found),
+            SessionId::Day(date, nth) => {
+                let on_day = record
+                    .get_entries()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, entry)| {
+                        entry.check_in.with_timezone(&ctx.timezone).date_naive() == date
+                    })
+                    .map(|(index, _)| index)
+                    .collect::<Vec<_>>();
+
+                match (nth, on_day.as_slice()) {
+                    (None, [index]) => Ok(*index),
+                    (None, []) => Err(not_found()),
+                    (None, _) => Err(error::Main::AmbiguousSession {
+                        date,
+                        count: on_day.len(),
+                    }),
+                    (Some(nth), _) => nth
+                        .checked_sub(1)
+                        .and_then(|nth| on_day.get(nth).copied())
+                        .ok_or_else(not_found),
+                }
+            }
+        }
+    }
+}
+
+impl clap::builder::ValueParserFactory for SessionId {
+    type Parser = Parser;
+    fn value_parser() -> Self::Parser {
+        Parser
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Parser;
+
+impl clap::builder::TypedValueParser for Parser {
+    type Value = SessionId;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        let inner = clap::builder::NonEmptyStringValueParser::new();
+        let val = inner.parse_ref(cmd, arg, value)?;
+
+        if let Ok(index) = val.parse() {
+            return Ok(SessionId::Index(index));
+        }
+
+        let (date, nth) = match val.split_once('#') {
+            Some((date, nth)) => (date, nth.parse().ok()),
+            None => (val.as_str(), None),
+        };
+        if let Ok(date) = NaiveDate::parse_from_str(date, "%F") {
+            if val.contains('#') == nth.is_some() {
+                return Ok(SessionId::Day(date, nth));
+            }
+        }
+
+        Err(super::invalid_value(cmd, arg, val))
+    }
+}
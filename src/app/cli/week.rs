@@ -0,0 +1,48 @@
+use chrono::{NaiveDate, Weekday};
+
+/// An ISO 8601 week, represented by its Monday
+#[derive(Clone)]
+pub struct Week(pub NaiveDate);
+
+impl Week {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (year, week) = value.split_once("-W")?;
+        let monday =
+            NaiveDate::from_isoywd_opt(year.parse().ok()?, week.parse().ok()?, Weekday::Mon)?;
+        Some(Week(monday))
+    }
+
+    pub fn last_day(&self) -> NaiveDate {
+        self.0 + chrono::Duration::days(6)
+    }
+}
+
+impl clap::builder::ValueParserFactory for Week {
+    type Parser = Parser;
+    fn value_parser() -> Self::Parser {
+        Parser
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Parser;
+
+impl clap::builder::TypedValueParser for Parser {
+    type Value = Week;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        let inner = clap::builder::NonEmptyStringValueParser::new();
+        let val = inner.parse_ref(cmd, arg, value)?;
+
+        if let Some(week) = Week::parse(&val) {
+            return Ok(week);
+        }
+
+        Err(super::invalid_value(cmd, arg, val))
+    }
+}
@@ -1 +1,633 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::Deserialize;
+
+use crate::{
+    app::cli::{DayResolution, RoundPer},
+    error::{self, Result},
+    money::Amount,
+    script_hook::Policy as HookPolicy,
+    time::rounding::{Policy, Rounding},
+};
+
+mod layer;
+
+use layer::Layer;
+
+const PROJECT_CONFIG_PATH: &str = ".punch_clock/config.toml";
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Editor command used by `edit`, takes precedence over `$EDITOR`
+    pub editor: Option<String>,
+    /// Default timezone from the tz database, used when neither `--tz` nor `--offset` is given
+    pub timezone: Option<chrono_tz::Tz>,
+    /// Default UTC offset, in hours or like "+05:30", used when there's no timezone
+    pub offset: Option<chrono::FixedOffset>,
+    pub calendar: Calendar,
+    pub day: Day,
+    pub formats: Formats,
+    pub hooks: Hooks,
+    pub check: Check,
+    pub backups: Backups,
+    pub idle: Idle,
+    pub breaks: Breaks,
+    pub schedule: Schedule,
+    pub invoice: Invoice,
+    /// Applied to the totals printed by `stats`, `calendar` and `day`
+    pub rounding: Policy,
+}
+
+#[derive(Debug, Clone)]
+pub struct Calendar {
+    pub width: usize,
+}
+
+impl Default for Calendar {
+    fn default() -> Self {
+        Self { width: 48 }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Day {
+    pub resolution: DayResolution,
+}
+
+impl Default for Day {
+    fn default() -> Self {
+        Self {
+            resolution: DayResolution::Hour,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Formats {
+    pub dump: String,
+    pub day: String,
+}
+
+impl Default for Formats {
+    fn default() -> Self {
+        Self {
+            dump: "%e %b %Y %I:%M%P %Z".to_owned(),
+            day: "%R".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Hooks {
+    pub enabled: bool,
+    /// What happens when a hook fails or times out
+    pub policy: HookPolicy,
+    /// Hooks still running after this long are killed, and count as failed
+    pub timeout: Duration,
+    /// `long-session` runs when punching out of a session longer than this
+    pub long_session: Duration,
+    /// Overrides for particular hooks, like `[hooks.events.before-in]`
+    pub events: BTreeMap<String, HookOverride>,
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            policy: HookPolicy::default(),
+            timeout: Duration::seconds(30),
+            long_session: Duration::hours(10),
+            events: BTreeMap::new(),
+        }
+    }
+}
+
+impl Hooks {
+    /// The policy and timeout for the hook called `name`
+    pub fn settings(&self, name: &str) -> (HookPolicy, Duration) {
+        let event = self.events.get(name);
+        (
+            event.and_then(|event| event.policy).unwrap_or(self.policy),
+            event
+                .and_then(|event| event.timeout)
+                .unwrap_or(self.timeout),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HookOverride {
+    pub policy: Option<HookPolicy>,
+    #[serde(deserialize_with = "crate::time::deserialize_optional_duration")]
+    pub timeout: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    /// Sessions longer than this are reported by `check`
+    pub max_session_length: chrono::Duration,
+}
+
+impl Default for Check {
+    fn default() -> Self {
+        Self {
+            max_session_length: chrono::Duration::hours(16),
+        }
+    }
+}
+
+/// When an open session is taken to be forgotten, and offered to be closed
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Idle {
+    /// Open sessions longer than this are past the limit
+    #[serde(deserialize_with = "crate::time::deserialize_optional_duration")]
+    pub max_session: Option<Duration>,
+    /// Open sessions with no activity, as noted by `ping`, for this long are idle
+    #[serde(deserialize_with = "crate::time::deserialize_optional_duration")]
+    pub after: Option<Duration>,
+}
+
+/// Mandatory breaks, checked by `stats` and `report`
+#[derive(Debug, Clone, Default)]
+pub struct Breaks {
+    pub rules: Vec<BreakRule>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BreakRule {
+    /// Days with more work than this need the break
+    #[serde(deserialize_with = "crate::time::deserialize_duration")]
+    pub after: Duration,
+    #[serde(deserialize_with = "crate::time::deserialize_duration")]
+    pub minimum: Duration,
+}
+
+impl Breaks {
+    /// The break time needed on a day with `worked` time, the largest of any rules that apply
+    pub fn required(&self, worked: Duration) -> Duration {
+        self.rules
+            .iter()
+            .filter(|rule| worked > rule.after)
+            .map(|rule| rule.minimum)
+            .max()
+            .unwrap_or_else(Duration::zero)
+    }
+
+    /// How far `rested` falls short of the break time needed after `worked`, if at all
+    pub fn shortfall(&self, worked: Duration, rested: Duration) -> Option<Duration> {
+        let required = self.required(worked);
+        (rested < required).then(|| required - rested)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Backups {
+    /// Number of snapshots of the record to keep, 0 disables backups
+    pub keep: usize,
+}
+
+impl Default for Backups {
+    fn default() -> Self {
+        Self { keep: 20 }
+    }
+}
+
+/// Defaults for `invoice`
+#[derive(Debug, Clone)]
+pub struct Invoice {
+    /// Hourly rate
+    pub rate: Option<Amount>,
+    pub currency: Option<String>,
+    /// Like "nearest:15m", "up:6m" or "down:1h"
+    pub round: Option<Rounding>,
+    pub round_per: RoundPer,
+}
+
+impl Default for Invoice {
+    fn default() -> Self {
+        Self {
+            rate: None,
+            currency: None,
+            round: None,
+            round_per: RoundPer::Session,
+        }
+    }
+}
+
+/// Target hours per weekday, and the days that have no target
+#[derive(Debug, Clone, Default)]
+pub struct Schedule {
+    /// First day counted by `balance`, instead of the first day in the record
+    pub start: Option<NaiveDate>,
+    pub monday: Duration,
+    pub tuesday: Duration,
+    pub wednesday: Duration,
+    pub thursday: Duration,
+    pub friday: Duration,
+    pub saturday: Duration,
+    pub sunday: Duration,
+    pub holidays: Vec<NaiveDate>,
+    pub leave: Vec<NaiveDate>,
+}
+
+impl Schedule {
+    /// Whether any weekday has a target
+    pub fn is_set(&self) -> bool {
+        [
+            self.monday,
+            self.tuesday,
+            self.wednesday,
+            self.thursday,
+            self.friday,
+            self.saturday,
+            self.sunday,
+        ]
+        .iter()
+        .any(|hours| !hours.is_zero())
+    }
+
+    /// Why `day` has no target, if it's a holiday or leave
+    pub fn day_off(&self, day: NaiveDate) -> Option<&'static str> {
+        if self.holidays.contains(&day) {
+            Some("holiday")
+        } else if self.leave.contains(&day) {
+            Some("leave")
+        } else {
+            None
+        }
+    }
+
+    /// How long should be worked on `day`
+    pub fn target(&self, day: NaiveDate) -> Duration {
+        if self.day_off(day).is_some() {
+            return Duration::zero();
+        }
+
+        match day.weekday() {
+            Weekday::Mon => self.monday,
+            Weekday::Tue => self.tuesday,
+            Weekday::Wed => self.wednesday,
+            Weekday::Thu => self.thursday,
+            Weekday::Fri => self.friday,
+            Weekday::Sat => self.saturday,
+            Weekday::Sun => self.sunday,
+        }
+    }
+}
+
+/// A date written as a TOML date or a "YYYY-MM-DD" string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DateValue {
+    Toml(toml::value::Datetime),
+    Text(String),
+}
+
+impl DateValue {
+    fn parse<E: serde::de::Error>(self) -> std::result::Result<NaiveDate, E> {
+        let text = match self {
+            DateValue::Toml(datetime) => datetime.to_string(),
+            DateValue::Text(text) => text,
+        };
+        NaiveDate::parse_from_str(&text, "%F").map_err(|_| {
+            E::custom(format!(
+                "invalid date {text:?}, expected something like \"2024-12-25\""
+            ))
+        })
+    }
+}
+
+fn deserialize_optional_date<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<NaiveDate>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    DateValue::deserialize(deserializer)?.parse().map(Some)
+}
+
+fn deserialize_optional_dates<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<Vec<NaiveDate>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Vec::<DateValue>::deserialize(deserializer)?
+        .into_iter()
+        .map(DateValue::parse)
+        .collect::<std::result::Result<_, _>>()
+        .map(Some)
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_home.join("punch_clock").join("config.toml"))
+}
+
+fn line_of_offset(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+fn parse_file(path: &Path) -> Result<Option<Layer>> {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let invalid = |err: toml::de::Error| error::Main::InvalidConfig {
+        path: path.to_path_buf(),
+        line: err.span().map(|span| line_of_offset(&source, span.start)),
+        message: err.message().to_owned(),
+    };
+
+    // Deserializing each file on its own means unknown keys are reported against the file they're in
+    toml::from_str(&source).map(Some).map_err(invalid)
+}
+
+impl Config {
+    /// Loads the user-level config, then the project config on top of it.
+    ///
+    /// Expects the current directory to be the project root.
+    pub fn load() -> Result<Self> {
+        let mut config = Self::default();
+
+        let paths = user_config_path()
+            .into_iter()
+            .chain(std::iter::once(PathBuf::from(PROJECT_CONFIG_PATH)));
+
+        for path in paths {
+            if let Some(layer) = parse_file(&path)? {
+                config.apply(layer);
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+impl std::str::FromStr for Config {
+    type Err = toml::de::Error;
+
+    /// A config from a single file's settings
+    fn from_str(source: &str) -> std::result::Result<Self, Self::Err> {
+        let mut config = Self::default();
+        config.apply(toml::from_str(source)?);
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn project_overrides_user() {
+        let mut config: Config = "editor = \"vim\"
+[calendar]
+width = 24
+[formats]
+day = \"%H\"
+[hooks.events.out]
+policy = \"warn\"
+"
+        .parse()
+        .unwrap();
+        config.apply(
+            toml::from_str(
+                "[calendar]
+width = 96
+[hooks.events.out]
+timeout = \"1m\"
+",
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(Some("vim"), config.editor.as_deref());
+        assert_eq!(96, config.calendar.width);
+        assert_eq!("%H", config.formats.day);
+        assert_eq!(Formats::default().dump, config.formats.dump);
+        assert_eq!(
+            (HookPolicy::Warn, Duration::minutes(1)),
+            config.hooks.settings("out")
+        );
+    }
+
+    #[test]
+    fn project_zone_overrides_user() {
+        let mut config: Config = "timezone = \"Asia/Kolkata\"".parse().unwrap();
+        config.apply(toml::from_str("offset = -3").unwrap());
+        assert_eq!(None, config.timezone);
+        assert_eq!(Some(-10_800), config.offset.map(|o| o.local_minus_utc()));
+
+        let mut config: Config = "offset = -3".parse().unwrap();
+        config.apply(toml::from_str("timezone = \"Asia/Kolkata\"").unwrap());
+        assert_eq!(Some(chrono_tz::Asia::Kolkata), config.timezone);
+        assert_eq!(None, config.offset);
+
+        // Settings that leave the zone alone keep the user's
+        config.apply(toml::from_str("editor = \"vim\"").unwrap());
+        assert_eq!(Some(chrono_tz::Asia::Kolkata), config.timezone);
+    }
+
+    #[test]
+    fn unknown_key_names_line() {
+        let dir = std::env::temp_dir().join("punch_clock_config_unknown_key");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(
+            &path,
+            "editor = \"vim\"
+
+[calendar]
+widht = 24
+",
+        )
+        .unwrap();
+
+        let err = parse_file(&path).unwrap_err();
+        let error::Main::InvalidConfig {
+            path: err_path,
+            line,
+            ..
+        } = err
+        else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(path, err_path);
+        assert_eq!(Some(4), line);
+    }
+
+    #[test]
+    fn timezone_and_offset() {
+        let config: Config = "timezone = \"Asia/Kolkata\"
+offset = \"+05:30\"
+"
+        .parse()
+        .unwrap();
+        assert_eq!(Some(chrono_tz::Asia::Kolkata), config.timezone);
+        assert_eq!(Some(19_800), config.offset.map(|o| o.local_minus_utc()));
+
+        let config: Config = "offset = -3".parse().unwrap();
+        assert_eq!(Some(-10_800), config.offset.map(|o| o.local_minus_utc()));
+
+        assert!("timezone = \"Mars/Olympus_Mons\""
+            .parse::<Config>()
+            .is_err());
+    }
+
+    #[test]
+    fn schedule() {
+        let config: Config = "[schedule]
+start = 2024-01-01
+monday = \"8h\"
+friday = \"4h30m\"
+holidays = [2024-01-01, \"2024-12-25\"]
+leave = [2024-01-05]
+"
+        .parse()
+        .unwrap();
+        let schedule = config.schedule;
+        let date = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+
+        assert!(schedule.is_set());
+        assert_eq!(Some(date(1, 1)), schedule.start);
+        assert_eq!(Some("holiday"), schedule.day_off(date(12, 25)));
+        assert_eq!(Duration::zero(), schedule.target(date(1, 1)));
+        assert_eq!(Duration::hours(8), schedule.target(date(1, 8)));
+        assert_eq!(Duration::zero(), schedule.target(date(1, 5)));
+        assert_eq!(Duration::minutes(270), schedule.target(date(1, 12)));
+        assert_eq!(Duration::zero(), schedule.target(date(1, 13)));
+
+        assert!(!Config::default().schedule.is_set());
+        assert!("[schedule]\nholidays = [\"25/12\"]"
+            .parse::<Config>()
+            .is_err());
+    }
+
+    #[test]
+    fn invoice() {
+        let config: Config = "[invoice]
+rate = 120.5
+currency = \"NZD\"
+round = \"up:6m\"
+round_per = \"total\"
+"
+        .parse()
+        .unwrap();
+        let invoice = config.invoice;
+
+        assert_eq!(
+            Some("120.50"),
+            invoice.rate.map(|rate| rate.to_string()).as_deref()
+        );
+        assert_eq!(
+            Some("up to the next 6m"),
+            invoice.round.map(|round| round.to_string()).as_deref()
+        );
+        assert!(matches!(invoice.round_per, RoundPer::Total));
+
+        assert!("[invoice]\nround = \"up\"".parse::<Config>().is_err());
+    }
+
+    #[test]
+    fn rounding() {
+        let config: Config = "[rounding]
+rule = \"nearest:15m\"
+per = \"day\"
+minimum = \"30m\"
+"
+        .parse()
+        .unwrap();
+        let policy = config.rounding;
+
+        assert_eq!(Some("nearest:15m".parse().unwrap()), policy.rule);
+        assert_eq!(crate::time::rounding::Scope::Day, policy.per);
+        assert_eq!(chrono::Duration::minutes(30), policy.minimum);
+
+        assert!(Config::default().rounding.rule.is_none());
+    }
+
+    #[test]
+    fn hook_overrides() {
+        let config: Config = "[hooks]
+policy = \"warn\"
+timeout = \"5s\"
+
+[hooks.events.before-in]
+policy = \"abort\"
+
+[hooks.events.out]
+timeout = \"1m\"
+"
+        .parse()
+        .unwrap();
+        let hooks = config.hooks;
+
+        assert_eq!(
+            (HookPolicy::Abort, chrono::Duration::seconds(5)),
+            hooks.settings("before-in")
+        );
+        assert_eq!(
+            (HookPolicy::Warn, chrono::Duration::minutes(1)),
+            hooks.settings("out")
+        );
+        assert_eq!(
+            (HookPolicy::Warn, chrono::Duration::seconds(5)),
+            hooks.settings("undo")
+        );
+
+        assert!("[hooks]\npolicy = \"retry\"".parse::<Config>().is_err());
+    }
+
+    #[test]
+    fn break_rules() {
+        let config: Config = "[breaks]
+rules = [
+    { after = \"6h\", minimum = \"30m\" },
+    { after = \"9h\", minimum = \"45m\" },
+]
+"
+        .parse()
+        .unwrap();
+        let breaks = config.breaks;
+
+        assert_eq!(
+            chrono::Duration::zero(),
+            breaks.required(chrono::Duration::hours(6))
+        );
+        assert_eq!(
+            chrono::Duration::minutes(30),
+            breaks.required(chrono::Duration::hours(8))
+        );
+        assert_eq!(
+            chrono::Duration::minutes(45),
+            breaks.required(chrono::Duration::hours(10))
+        );
+        assert_eq!(
+            Some(chrono::Duration::minutes(15)),
+            breaks.shortfall(chrono::Duration::hours(8), chrono::Duration::minutes(15))
+        );
+        assert_eq!(
+            None,
+            breaks.shortfall(chrono::Duration::hours(8), chrono::Duration::minutes(30))
+        );
+
+        assert!("[breaks]\nrules = [{ after = \"6h\" }]"
+            .parse::<Config>()
+            .is_err());
+    }
+}
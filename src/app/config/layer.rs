@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+
+use chrono::{Duration, FixedOffset, NaiveDate};
+use serde::Deserialize;
+
+use super::{deserialize_optional_date, BreakRule, Config, HookOverride, Idle};
+use crate::{
+    app::cli::{DayResolution, RoundPer},
+    money::Amount,
+    script_hook::Policy as HookPolicy,
+    time::rounding::{Rounding, Scope},
+};
+
+/// The settings in one config file, where anything left out is `None` and left to the files below it
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Layer {
+    editor: Option<String>,
+    #[serde(deserialize_with = "crate::time::deserialize_timezone")]
+    timezone: Option<chrono_tz::Tz>,
+    #[serde(deserialize_with = "crate::time::deserialize_offset")]
+    offset: Option<FixedOffset>,
+    calendar: CalendarLayer,
+    day: DayLayer,
+    formats: FormatsLayer,
+    hooks: HooksLayer,
+    check: CheckLayer,
+    backups: BackupsLayer,
+    idle: Idle,
+    breaks: BreaksLayer,
+    schedule: ScheduleLayer,
+    invoice: InvoiceLayer,
+    rounding: RoundingLayer,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CalendarLayer {
+    width: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DayLayer {
+    resolution: Option<DayResolution>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FormatsLayer {
+    dump: Option<String>,
+    day: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct HooksLayer {
+    enabled: Option<bool>,
+    policy: Option<HookPolicy>,
+    #[serde(deserialize_with = "crate::time::deserialize_optional_duration")]
+    timeout: Option<Duration>,
+    #[serde(deserialize_with = "crate::time::deserialize_optional_duration")]
+    long_session: Option<Duration>,
+    events: BTreeMap<String, HookOverride>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CheckLayer {
+    #[serde(deserialize_with = "crate::time::deserialize_optional_duration")]
+    max_session_length: Option<Duration>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct BackupsLayer {
+    keep: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct BreaksLayer {
+    rules: Option<Vec<BreakRule>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ScheduleLayer {
+    #[serde(deserialize_with = "deserialize_optional_date")]
+    start: Option<NaiveDate>,
+    #[serde(deserialize_with = "crate::time::deserialize_optional_duration")]
+    monday: Option<Duration>,
+    #[serde(deserialize_with = "crate::time::deserialize_optional_duration")]
+    tuesday: Option<Duration>,
+    #[serde(deserialize_with = "crate::time::deserialize_optional_duration")]
+    wednesday: Option<Duration>,
+    #[serde(deserialize_with = "crate::time::deserialize_optional_duration")]
+    thursday: Option<Duration>,
+    #[serde(deserialize_with = "crate::time::deserialize_optional_duration")]
+    friday: Option<Duration>,
+    #[serde(deserialize_with = "crate::time::deserialize_optional_duration")]
+    saturday: Option<Duration>,
+    #[serde(deserialize_with = "crate::time::deserialize_optional_duration")]
+    sunday: Option<Duration>,
+    #[serde(deserialize_with = "super::deserialize_optional_dates")]
+    holidays: Option<Vec<NaiveDate>>,
+    #[serde(deserialize_with = "super::deserialize_optional_dates")]
+    leave: Option<Vec<NaiveDate>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct InvoiceLayer {
+    #[serde(deserialize_with = "crate::money::deserialize_amount")]
+    rate: Option<Amount>,
+    currency: Option<String>,
+    #[serde(deserialize_with = "crate::time::rounding::deserialize_rounding")]
+    round: Option<Rounding>,
+    round_per: Option<RoundPer>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RoundingLayer {
+    #[serde(deserialize_with = "crate::time::rounding::deserialize_rounding")]
+    rule: Option<Rounding>,
+    per: Option<Scope>,
+    #[serde(deserialize_with = "crate::time::deserialize_optional_duration")]
+    minimum: Option<Duration>,
+}
+
+/// Replaces `value` with `layer`'s, if it has one
+fn set<T>(value: &mut T, layer: Option<T>) {
+    if let Some(layer) = layer {
+        *value = layer;
+    }
+}
+
+/// Replaces `value` with `layer`'s, if it has one, for settings that are optional in `Config` too
+fn set_optional<T>(value: &mut Option<T>, layer: Option<T>) {
+    if layer.is_some() {
+        *value = layer;
+    }
+}
+
+impl Config {
+    /// Puts the settings in `layer` on top of the current ones
+    pub fn apply(&mut self, layer: Layer) {
+        set_optional(&mut self.editor, layer.editor);
+        // Either kind of zone replaces both from the layers below, so a project's offset beats a user's timezone
+        if layer.timezone.is_some() || layer.offset.is_some() {
+            self.timezone = layer.timezone;
+            self.offset = layer.offset;
+        }
+
+        set(&mut self.calendar.width, layer.calendar.width);
+        set(&mut self.day.resolution, layer.day.resolution);
+        set(&mut self.formats.dump, layer.formats.dump);
+        set(&mut self.formats.day, layer.formats.day);
+
+        let hooks = layer.hooks;
+        set(&mut self.hooks.enabled, hooks.enabled);
+        set(&mut self.hooks.policy, hooks.policy);
+        set(&mut self.hooks.timeout, hooks.timeout);
+        set(&mut self.hooks.long_session, hooks.long_session);
+        for (name, event) in hooks.events {
+            let existing = self.hooks.events.entry(name).or_default();
+            set_optional(&mut existing.policy, event.policy);
+            set_optional(&mut existing.timeout, event.timeout);
+        }
+
+        set(
+            &mut self.check.max_session_length,
+            layer.check.max_session_length,
+        );
+        set(&mut self.backups.keep, layer.backups.keep);
+        set_optional(&mut self.idle.max_session, layer.idle.max_session);
+        set_optional(&mut self.idle.after, layer.idle.after);
+        set(&mut self.breaks.rules, layer.breaks.rules);
+
+        let schedule = layer.schedule;
+        set_optional(&mut self.schedule.start, schedule.start);
+        set(&mut self.schedule.monday, schedule.monday);
+        set(&mut self.schedule.tuesday, schedule.tuesday);
+        set(&mut self.schedule.wednesday, schedule.wednesday);
+        set(&mut self.schedule.thursday, schedule.thursday);
+        set(&mut self.schedule.friday, schedule.friday);
+        set(&mut self.schedule.saturday, schedule.saturday);
+        set(&mut self.schedule.sunday, schedule.sunday);
+        set(&mut self.schedule.holidays, schedule.holidays);
+        set(&mut self.schedule.leave, schedule.leave);
+
+        let invoice = layer.invoice;
+        set_optional(&mut self.invoice.rate, invoice.rate);
+        set_optional(&mut self.invoice.currency, invoice.currency);
+        set_optional(&mut self.invoice.round, invoice.round);
+        set(&mut self.invoice.round_per, invoice.round_per);
+
+        set_optional(&mut self.rounding.rule, layer.rounding.rule);
+        set(&mut self.rounding.per, layer.rounding.per);
+        set(&mut self.rounding.minimum, layer.rounding.minimum);
+    }
+}
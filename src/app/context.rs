@@ -1,22 +1,34 @@
-use crate::{
-    error::{self, Result},
-    time::ContextTimeZone,
-};
+use crate::{app::config::Config, time::ContextTimeZone};
 
 pub struct Context<Tz: ContextTimeZone> {
-    pub editor_path: String,
+    pub editor_path: Option<String>,
     pub timezone: Tz,
     pub skip_hooks: bool,
+    pub config: Config,
 }
 
 impl<Tz: ContextTimeZone> Context<Tz> {
-    pub fn init(timezone: Tz, skip_hooks: bool) -> Result<Self> {
-        let editor_path = std::env::var("EDITOR").map_err(|_| error::Main::MissingEditorPath)?;
+    pub fn init(timezone: Tz, skip_hooks: bool, config: Config) -> Self {
+        let editor_path = config
+            .editor
+            .clone()
+            .or_else(|| std::env::var("EDITOR").ok());
 
-        Ok(Context {
+        Context {
             editor_path,
             timezone,
-            skip_hooks,
-        })
+            skip_hooks: skip_hooks || !config.hooks.enabled,
+            config,
+        }
+    }
+
+    /// The same context, with dates and times in `timezone` instead
+    pub fn with_timezone<Tz2: ContextTimeZone>(&self, timezone: Tz2) -> Context<Tz2> {
+        Context {
+            editor_path: self.editor_path.clone(),
+            timezone,
+            skip_hooks: self.skip_hooks,
+            config: self.config.clone(),
+        }
     }
 }
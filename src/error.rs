@@ -1,3 +1,5 @@
+use std::path::{Path, PathBuf};
+
 use crate::script_hook;
 
 pub type Result<T, E = Main> = std::result::Result<T, E>;
@@ -22,18 +24,76 @@ pub enum Main {
     #[error("Attempted to create a datetime range with the end before the start. start = {start} end = {end}")]
     DateTimeRangeEndBeforeStart { start: String, end: String },
 
-    #[error("An entry has an invalid number of tokens")]
-    EntryIncorrectNumberOfTokens,
-
     #[error("An entry is defined with an invalid number of lines")]
     EntryIncorrectNumberOfLines,
 
+    #[error("Line {line} of the record: {source}\n{text}")]
+    RecordLine {
+        line: usize,
+        text: String,
+        source: Box<Main>,
+    },
+
+    #[error("The record was changed by something else since it was loaded, so it wasn't saved. Please try again.")]
+    RecordChangedOnDisk,
+
+    #[error("No backup with id {0:?}, see `backups list`")]
+    NoSuchBackup(String),
+
+    #[error("Found {0} problem(s) in the record")]
+    RecordProblems(usize),
+
+    #[error("{0} is in the future.")]
+    TimeInFuture(String),
+
+    #[error("{time} is before the last check-out at {check_out}.")]
+    BeforeLastCheckOut { time: String, check_out: String },
+
+    #[error("{time} is before the current session's check-in at {check_in}.")]
+    BeforeCheckIn { time: String, check_in: String },
+
+    #[error("{0} doesn't exist, or is ambiguous, in the current timezone.")]
+    InvalidLocalTime(String),
+
+    #[error("The session overlaps another one that starts at {0}, use --force to add it anyway.")]
+    OverlapsSession(String),
+
+    #[error("No finished session {0}, see `session list`")]
+    NoSuchSession(String),
+
+    #[error("There are {count} sessions on {date}, pick one with {date}#n")]
+    AmbiguousSession {
+        date: chrono::NaiveDate,
+        count: usize,
+    },
+
+    #[error("Can only split a session at a time between its start and end.")]
+    SplitOutsideSession,
+
+    #[error("Only consecutive sessions can be merged.")]
+    SessionsNotConsecutive,
+
     #[error("Not currently clocked-in.")]
     NotClockedIn,
 
     #[error("Already clocked-in.")]
     AlreadyClockedIn,
 
+    #[error("Already on a break since {0}.")]
+    AlreadyOnBreak(String),
+
+    #[error("Not currently on a break.")]
+    NotOnBreak,
+
+    #[error("{time} is before the current break started at {start}.")]
+    BeforeBreakStart { time: String, start: String },
+
+    #[error("{time} is before the last break ended at {end}.")]
+    BeforeLastBreak { time: String, end: String },
+
+    #[error("Invalid break: {0}")]
+    InvalidBreak(String),
+
     #[error("Failed to parse an integer: {0}")]
     ParseInt(#[from] std::num::ParseIntError),
 
@@ -46,7 +106,19 @@ pub enum Main {
     #[error("Formatting error: {0}")]
     Format(#[from] std::fmt::Error),
 
-    #[error("Please set the path of your default editor using the $EDITOR environment variable")]
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+
+    #[error("Failed to import {format} data: {message}")]
+    InvalidImport {
+        format: &'static str,
+        message: String,
+    },
+
+    #[error("Please set the path of your default editor using `editor` in config, or the $EDITOR environment variable")]
     MissingEditorPath,
 
     #[error("The editor subprocess exited unsuccessfully.")]
@@ -58,9 +130,6 @@ pub enum Main {
     #[error("Did not find file in path by prefix: {0}")]
     NoPrefixInPath(String),
 
-    #[error("Asked for an out-of-bounds timezone offset: {0}")]
-    TimezoneOutOfRange(i32),
-
     #[error("Date out of range")]
     DateOutOfRange,
 
@@ -70,6 +139,35 @@ pub enum Main {
     #[error("Comment must not contain newlines")]
     CommentWithNewlines,
 
+    #[error("Project names must not contain newlines: {0:?}")]
+    InvalidProject(String),
+
+    #[error("Tags must be non-empty and must not contain whitespace: {0:?}")]
+    InvalidTag(String),
+
+    #[error("Unrecognised annotation in record: {0}")]
+    UnknownAnnotation(String),
+
     #[error("Failed to execute script hook: {0}")]
     ScriptHook(#[from] script_hook::Error),
+
+    #[error("No hourly rate given, pass `--rate` or set `invoice.rate` in config")]
+    MissingRate,
+
+    #[error("No schedule is set, give target hours for each weekday under `[schedule]` in config")]
+    NoSchedule,
+
+    #[error("Invalid config at {}: {message}", config_location(path, *line))]
+    InvalidConfig {
+        path: PathBuf,
+        line: Option<usize>,
+        message: String,
+    },
+}
+
+fn config_location(path: &Path, line: Option<usize>) -> String {
+    match line {
+        Some(line) => format!("{}:{line}", path.display()),
+        None => path.display().to_string(),
+    }
 }
@@ -2,50 +2,72 @@ mod action;
 mod app;
 mod error;
 mod fs;
+mod money;
 mod range;
 mod record;
 mod script_hook;
 mod string;
 mod time;
 
-use chrono::{FixedOffset, Local, Utc};
+use std::process::ExitCode;
+
+use chrono::Local;
 use clap::Parser;
 
 static GIT_REVISION: &str = env!("PUNCH_CLOCK_GIT_REVISION");
 static LONG_VERSION: &str = env!("PUNCH_CLOCK_LONG_VERSION");
 
-fn main() {
-    if let Err(err) = run() {
-        println!("Error: {err}");
-        std::process::exit(1);
+/// Every error exits with this, clear of the codes `status` exits with for each state
+const ERROR_EXIT_CODE: u8 = 3;
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(code) => code,
+        Err(err) => {
+            println!("Error: {err}");
+            ExitCode::from(ERROR_EXIT_CODE)
+        }
     }
 }
 
-fn run() -> error::Result<()> {
-    let cli = app::cli::Base::parse();
+fn run() -> error::Result<ExitCode> {
+    let cli = match app::cli::Base::try_parse() {
+        Ok(cli) => cli,
+        // Usage errors would otherwise exit with 2, like `status` for an empty record
+        Err(err) if err.use_stderr() => {
+            err.print()?;
+            return Ok(ExitCode::from(ERROR_EXIT_CODE));
+        }
+        Err(err) => err.exit(),
+    };
 
     std::env::set_current_dir(fs::file_location_in_path_by_prefix(".punch_clock")?)?;
 
     if cli.init {
-        record::Record::init()?;
+        record::storage::init()?;
     }
 
-    let Some(record) = record::Record::<Utc>::load()? else {
-        return Err(error::Main::Uninitialized);
-    };
+    let config = app::config::Config::load()?;
 
-    let action = cli.action.unwrap_or(app::cli::Action::Status);
+    let action = cli.action.unwrap_or(app::cli::Action::Status {
+        format: app::cli::StatusFormat::Text,
+    });
 
-    if let Some(offset) = cli.offset {
-        let ctx = app::Context::init(
-            FixedOffset::east_opt(offset * 3600).ok_or(error::Main::TimezoneOutOfRange(offset))?,
-            cli.skip_hooks,
-        )?;
-        action::run(&ctx, &action, record)?;
+    // Flags take precedence over config, and a named timezone over a fixed offset given alongside it
+    let (timezone, offset) = if cli.timezone.is_some() || cli.offset.is_some() {
+        (cli.timezone, cli.offset)
     } else {
-        let ctx = app::Context::init(Local, cli.skip_hooks)?;
-        action::run(&ctx, &action, record)?;
-    }
+        (config.timezone, config.offset)
+    };
 
-    Ok(())
+    if let Some(timezone) = timezone {
+        let ctx = app::Context::init(timezone, cli.skip_hooks, config);
+        action::run(&ctx, &action)
+    } else if let Some(offset) = offset {
+        let ctx = app::Context::init(offset, cli.skip_hooks, config);
+        action::run(&ctx, &action)
+    } else {
+        let ctx = app::Context::init(Local, cli.skip_hooks, config);
+        action::run(&ctx, &action)
+    }
 }
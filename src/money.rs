@@ -0,0 +1,118 @@
+use std::{fmt::Display, str::FromStr};
+
+use chrono::Duration;
+
+/// An amount of money in hundredths, like cents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Amount(i64);
+
+impl Amount {
+    /// What `duration` costs at `self` per hour, to the nearest hundredth
+    pub fn for_duration(self, duration: Duration) -> Self {
+        let hour_millis = 3_600_000;
+        let cost = i128::from(self.0) * i128::from(duration.num_milliseconds());
+        let rounded = (cost + hour_millis / 2).div_euclid(hour_millis);
+        Self(i64::try_from(rounded).unwrap_or(i64::MAX))
+    }
+}
+
+impl std::ops::Add for Amount {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl std::iter::Sum for Amount {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |total, amount| total + amount)
+    }
+}
+
+/// Parses amounts like `120`, `120.5` or `99.95`
+impl FromStr for Amount {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            || format!("invalid amount {value:?}, expected something like \"120\" or \"99.95\"");
+
+        let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+        if whole.is_empty()
+            || fraction.len() > 2
+            || !whole
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let whole: i64 = whole.parse().map_err(|_| invalid())?;
+        let fraction: i64 = format!("{fraction:0<2}").parse().map_err(|_| invalid())?;
+        whole
+            .checked_mul(100)
+            .and_then(|whole| whole.checked_add(fraction))
+            .map(Self)
+            .ok_or_else(invalid)
+    }
+}
+
+impl Display for Amount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let hundredths = self.0.unsigned_abs();
+        write!(f, "{sign}{}.{:02}", hundredths / 100, hundredths % 100)
+    }
+}
+
+/// Accepts a TOML number as well as a string, so `rate = 120` works
+pub fn deserialize_amount<'de, D>(deserializer: D) -> Result<Option<Amount>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        Integer(u32),
+        Float(f64),
+        Text(String),
+    }
+
+    let value = match <Value as serde::Deserialize>::deserialize(deserializer)? {
+        Value::Integer(value) => value.to_string(),
+        Value::Float(value) => value.to_string(),
+        Value::Text(value) => value,
+    };
+    value.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+    use pretty_assertions::assert_eq;
+
+    use super::Amount;
+
+    #[test]
+    fn parse_and_display() {
+        assert_eq!("120.00", "120".parse::<Amount>().unwrap().to_string());
+        assert_eq!("120.50", "120.5".parse::<Amount>().unwrap().to_string());
+        assert_eq!("0.05", "0.05".parse::<Amount>().unwrap().to_string());
+        assert!("12.345".parse::<Amount>().is_err());
+        assert!("-3".parse::<Amount>().is_err());
+        assert!(".5".parse::<Amount>().is_err());
+    }
+
+    #[test]
+    fn for_duration() {
+        let rate: Amount = "120".parse().unwrap();
+        assert_eq!(
+            "180.00",
+            rate.for_duration(Duration::minutes(90)).to_string()
+        );
+        // 20 seconds at 120 an hour is 0.666...
+        assert_eq!("0.67", rate.for_duration(Duration::seconds(20)).to_string());
+    }
+}
@@ -1,29 +1,62 @@
+pub mod breaks;
 pub mod display;
+pub mod labels;
+pub mod parse;
+pub mod storage;
 
 use std::{
     collections::VecDeque,
     fmt::{Display, Write},
-    fs,
     ops::RangeInclusive,
 };
 
-use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate, TimeZone, Utc};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, Offset, TimeZone, Utc};
 use context::Context;
 
 use crate::{
     app::context,
     error::{self, Result},
-    time::{range::DateTimeRange, ContextTimeZone, NaiveDateOperations},
+    time::{local_display, range::DateTimeRange, ContextTimeZone, NaiveDateOperations},
 };
+pub use breaks::Break;
+pub use labels::{Filter, Labels};
+pub use storage::Storage;
 
-// FIXME: I'm thinking Entry ought to just be completely replaced by DateTimeRange
-#[derive(Clone)]
+/// The UTC offsets an entry was punched in and out with, kept whatever timezone it's viewed in
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct RecordedOffsets {
+    pub check_in: FixedOffset,
+    pub check_out: FixedOffset,
+}
+
+/// A session recorded in a different UTC offset than the one before it
+#[derive(Clone, Copy)]
 #[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+pub struct OffsetChange {
+    /// Date of the first time recorded in the new offset, in that offset
+    pub date: NaiveDate,
+    pub from: FixedOffset,
+    pub to: FixedOffset,
+}
+
+impl Display for OffsetChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "offset changed from {} to {}", self.from, self.to)
+    }
+}
+
+// FIXME: I'm thinking Entry ought to just be completely replaced by DateTimeRange
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(test, derive(Debug))]
 pub struct Entry<Tz: TimeZone> {
     pub check_in: DateTime<Tz>,
     work_time_millis: u32,
     pub in_comment: Option<String>,
     pub out_comment: Option<String>,
+    pub labels: Labels,
+    pub recorded_offsets: RecordedOffsets,
+    pub breaks: Vec<Break<Tz>>,
 }
 
 impl<Tz: TimeZone> Entry<Tz> {
@@ -37,6 +70,11 @@ impl<Tz: TimeZone> Entry<Tz> {
             return Err(error::Main::CheckOutBeforeCheckIn);
         }
 
+        let recorded_offsets = RecordedOffsets {
+            check_in: check_in.offset().fix(),
+            check_out: check_out.offset().fix(),
+        };
+
         let work_time_millis = check_out
             .signed_duration_since(check_in.clone())
             .num_milliseconds()
@@ -47,28 +85,74 @@ impl<Tz: TimeZone> Entry<Tz> {
             work_time_millis,
             in_comment,
             out_comment,
+            labels: Labels::default(),
+            recorded_offsets,
+            breaks: vec![],
         })
     }
 
+    pub fn with_labels(self, labels: Labels) -> Self {
+        Self { labels, ..self }
+    }
+
+    /// Replaces the breaks taken during this entry, which must all have ended within it
+    pub fn with_breaks(self, breaks: Vec<Break<Tz>>) -> Result<Self>
+    where
+        Tz::Offset: Display,
+    {
+        breaks::validate(&breaks, &self.check_in, Some(&self.get_check_out()?))?;
+        Ok(Self { breaks, ..self })
+    }
+
     pub fn with_timezone<Tz2: TimeZone>(self, tz: &Tz2) -> Entry<Tz2> {
         let Self {
             check_in,
             work_time_millis,
             in_comment,
             out_comment,
+            labels,
+            recorded_offsets,
+            breaks,
         } = self;
         Entry {
             check_in: check_in.with_timezone(tz),
             work_time_millis,
             in_comment,
             out_comment,
+            labels,
+            recorded_offsets,
+            breaks: breaks
+                .into_iter()
+                .map(|pause| pause.with_timezone(tz))
+                .collect(),
         }
     }
 
+    /// The check-in in the offset it was recorded in
+    pub fn recorded_check_in(&self) -> DateTime<FixedOffset> {
+        self.check_in.with_timezone(&self.recorded_offsets.check_in)
+    }
+
+    /// The check-out in the offset it was recorded in
+    pub fn recorded_check_out(&self) -> Result<DateTime<FixedOffset>> {
+        Ok(self
+            .get_check_out()?
+            .with_timezone(&self.recorded_offsets.check_out))
+    }
+
+    /// The time from check-in to check-out, breaks included
     pub fn get_work_duration(&self) -> Duration {
         Duration::milliseconds(self.work_time_millis.into())
     }
 
+    /// The time spent on breaks during this entry
+    pub fn get_break_duration(&self) -> Duration {
+        self.breaks
+            .iter()
+            .map(|pause| pause.duration(&self.check_in))
+            .sum()
+    }
+
     pub fn get_check_out(&self) -> Result<DateTime<Tz>> {
         // FIXME: Why not just `self.check_in + self.get_work_duration()`?
         self.check_in
@@ -76,95 +160,132 @@ impl<Tz: TimeZone> Entry<Tz> {
             .checked_add_signed(self.get_work_duration())
             .ok_or(error::Main::DateTimeOverflow)
     }
-}
 
-impl Entry<FixedOffset> {
-    fn from_lines(check_in_line: &str, check_out_line: &str) -> Result<Entry<FixedOffset>> {
-        let check_in_parts = split_sparse_tokens(check_in_line, ' ');
-        let check_out_parts = split_sparse_tokens(check_out_line, ' ');
+    /// Whether this entry and `other` share any time, excluding touching ends
+    pub fn overlaps(&self, other: &Entry<Tz>) -> Result<bool> {
+        Ok(self.check_in < other.get_check_out()? && other.check_in < self.get_check_out()?)
+    }
 
-        let (check_in, in_comment) = match check_in_parts.as_slice() {
-            [check_in] => (check_in, None),
-            [check_in, comment @ ..] => (check_in, Some(comment.join(" "))),
-            _ => {
-                return Err(error::Main::EntryIncorrectNumberOfTokens);
-            }
-        };
+    /// Whether this entry covers exactly the same time as `other`
+    pub fn same_time_as(&self, other: &Entry<Tz>) -> bool {
+        self.check_in == other.check_in && self.work_time_millis == other.work_time_millis
+    }
+}
 
-        let (check_out, out_comment) = match check_out_parts.as_slice() {
-            [check_out] => (check_out, None),
-            [check_out, comment @ ..] => (check_out, Some(comment.join(" "))),
-            _ => {
-                return Err(error::Main::EntryIncorrectNumberOfTokens);
+impl<Tz: TimeZone> Entry<Tz>
+where
+    Tz::Offset: Display,
+{
+    /// The time worked, split around any breaks
+    pub fn into_work_ranges(self) -> Result<Vec<DateTimeRange<Tz>>> {
+        if self.breaks.is_empty() {
+            return Ok(vec![DateTimeRange::from(self)]);
+        }
+
+        let check_out = self.get_check_out()?;
+        let mut ranges = vec![];
+        let mut start = self.check_in.clone();
+        for pause in self.breaks {
+            if start < pause.start {
+                ranges.push(
+                    DateTimeRange::new(start, pause.start.clone())?
+                        .in_session(self.check_in.clone()),
+                );
             }
-        };
+            start = pause.end_or(&check_out);
+        }
+        if start < check_out {
+            ranges.push(DateTimeRange::new(start, check_out)?.in_session(self.check_in));
+        }
 
-        Self::from_tokens(check_in, check_out, in_comment, out_comment)
+        Ok(ranges)
     }
 
-    fn from_tokens(
-        check_in: &str,
-        check_out: &str,
-        in_comment: Option<String>,
-        out_comment: Option<String>,
-    ) -> Result<Entry<FixedOffset>> {
-        let check_in = DateTime::parse_from_rfc3339(check_in)?;
-        let check_out = DateTime::parse_from_rfc3339(check_out)?;
-
-        Entry::try_new(check_in, check_out, in_comment, out_comment)
+    /// The breaks taken during this entry, leaving out any of no length
+    pub fn break_ranges(&self) -> Result<Vec<DateTimeRange<Tz>>> {
+        let check_out = self.get_check_out()?;
+        self.breaks
+            .iter()
+            .map(|pause| (pause.start.clone(), pause.end_or(&check_out)))
+            .filter(|(start, end)| start < end)
+            .map(|(start, end)| DateTimeRange::new(start, end))
+            .collect()
     }
 }
 
-fn split_sparse_tokens_str<'a>(line: &'a str, pat: &'static str) -> Vec<&'a str> {
-    line.split(pat)
-        .map(str::trim)
-        .filter(|t| !t.is_empty())
-        .collect()
+/// A session that has been clocked into, but not yet out of
+#[derive(Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+pub struct Session<Tz: TimeZone> {
+    pub check_in: DateTime<Tz>,
+    pub in_comment: Option<String>,
+    pub labels: Labels,
+    /// The UTC offset the session was punched in with
+    pub recorded_offset: FixedOffset,
+    pub breaks: Vec<Break<Tz>>,
 }
 
-fn split_sparse_tokens(line: &str, pat: char) -> Vec<&str> {
-    line.split(pat)
-        .map(str::trim)
-        .filter(|t| !t.is_empty())
-        .collect()
-}
+impl<Tz: TimeZone> Session<Tz> {
+    pub fn with_timezone<Tz2: TimeZone>(self, tz: &Tz2) -> Session<Tz2> {
+        Session {
+            check_in: self.check_in.with_timezone(tz),
+            in_comment: self.in_comment,
+            labels: self.labels,
+            recorded_offset: self.recorded_offset,
+            breaks: self
+                .breaks
+                .into_iter()
+                .map(|pause| pause.with_timezone(tz))
+                .collect(),
+        }
+    }
 
-impl TryFrom<&str> for Entry<FixedOffset> {
-    type Error = error::Main;
+    /// The break being taken right now, if any
+    pub fn open_break(&self) -> Option<&Break<Tz>> {
+        self.breaks.last().filter(|pause| pause.end.is_none())
+    }
 
-    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
-        let lines = split_sparse_tokens(value, '\n');
-        let [check_in_line, check_out_line] = lines.as_slice() else {
-            return Err(error::Main::EntryIncorrectNumberOfLines);
-        };
-        let check_in_parts = split_sparse_tokens(check_in_line, ' ');
-        let check_out_parts = split_sparse_tokens(check_out_line, ' ');
-
-        let (check_in, in_comment) = match check_in_parts.as_slice() {
-            [check_in] => (check_in, None),
-            [check_in, comment @ ..] => (check_in, Some(comment.join(" "))),
-            _ => {
-                return Err(error::Main::EntryIncorrectNumberOfTokens);
-            }
-        };
+    /// The time spent on breaks so far, counting an open one up to `now`
+    pub fn get_break_duration(&self, now: &DateTime<Tz>) -> Duration {
+        self.breaks.iter().map(|pause| pause.duration(now)).sum()
+    }
 
-        let (check_out, out_comment) = match check_out_parts.as_slice() {
-            [check_out] => (check_out, None),
-            [check_out, comment @ ..] => (check_out, Some(comment.join(" "))),
-            _ => {
-                return Err(error::Main::EntryIncorrectNumberOfTokens);
-            }
-        };
+    /// The last time punched during the session, either its check-in or that of a break
+    pub fn last_datetime(&self) -> DateTime<Tz> {
+        self.breaks.last().map_or(self.check_in.clone(), |pause| {
+            pause.end.clone().unwrap_or(pause.start.clone())
+        })
+    }
 
-        Self::from_tokens(check_in, check_out, in_comment, out_comment)
+    /// The check-in in the offset it was recorded in
+    pub fn recorded_check_in(&self) -> DateTime<FixedOffset> {
+        self.check_in.with_timezone(&self.recorded_offset)
+    }
+
+    /// Ends the session, recording the offset of `check_out`.
+    ///
+    /// A break still open at `check_out` ends with it, and any starting later are dropped.
+    pub fn into_entry(
+        self,
+        check_out: DateTime<Tz>,
+        out_comment: Option<String>,
+    ) -> Result<Entry<Tz>> {
+        let breaks = self
+            .breaks
+            .into_iter()
+            .filter_map(|pause| pause.ended_by(&check_out))
+            .collect();
+        let mut entry = Entry::try_new(self.check_in, check_out, self.in_comment, out_comment)?
+            .with_labels(self.labels);
+        entry.recorded_offsets.check_in = self.recorded_offset;
+        entry.breaks = breaks;
+        Ok(entry)
     }
 }
 
 pub enum Latest<'a, Tz: TimeZone> {
     Entry(&'a Entry<Tz>),
-    #[allow(dead_code)]
-    // It's unused, but it represents the `in_comment` of the latest entry, so I'll keep it here
-    Current(&'a DateTime<Tz>, Option<&'a str>),
+    Current(&'a Session<Tz>),
     None,
 }
 
@@ -172,7 +293,7 @@ pub enum Latest<'a, Tz: TimeZone> {
 #[cfg_attr(test, derive(Debug))]
 pub struct Record<Tz: TimeZone> {
     entries: Vec<Entry<Tz>>,
-    current_session: Option<(DateTime<Tz>, Option<String>)>,
+    current_session: Option<Session<Tz>>,
 }
 
 impl<Tz: TimeZone> Record<Tz> {
@@ -187,32 +308,170 @@ impl<Tz: TimeZone> Record<Tz> {
             .collect::<Vec<_>>();
         Record {
             entries,
-            current_session: current_session.map(|(cs, comment)| (cs.with_timezone(tz), comment)),
+            current_session: current_session.map(|cs| cs.with_timezone(tz)),
+        }
+    }
+
+    /// Moves every item to `offset`, keeping the wall-clock time it was recorded at rather than the instant.
+    ///
+    /// Sessions keep their length, so one that crossed an offset change ends at a different wall-clock time than it was punched out at.
+    pub fn shifted_to_recorded(self, offset: &FixedOffset) -> Result<Record<FixedOffset>> {
+        let shift = |recorded: DateTime<FixedOffset>| {
+            recorded
+                .naive_local()
+                .and_local_timezone(*offset)
+                .single()
+                .ok_or(error::Main::DateOutOfRange)
+        };
+
+        let mut entries = vec![];
+        for entry in self.entries {
+            let check_in = shift(entry.recorded_check_in())?;
+            let entry = entry.with_timezone(offset);
+            let delta = check_in - entry.check_in;
+            let breaks = entry
+                .breaks
+                .iter()
+                .cloned()
+                .map(|pause| pause.shifted(delta))
+                .collect();
+            entries.push(Entry {
+                check_in,
+                breaks,
+                ..entry
+            });
+        }
+        entries.sort_by_key(|entry| entry.check_in);
+
+        let current_session = match self.current_session {
+            Some(session) => {
+                let check_in = shift(session.recorded_check_in())?;
+                let session = session.with_timezone(offset);
+                let delta = check_in - session.check_in;
+                let breaks = session
+                    .breaks
+                    .iter()
+                    .cloned()
+                    .map(|pause| pause.shifted(delta))
+                    .collect();
+                Some(Session {
+                    check_in,
+                    breaks,
+                    ..session
+                })
+            }
+            None => None,
+        };
+
+        Ok(Record {
+            entries,
+            current_session,
+        })
+    }
+
+    /// Every point where the recorded offset differs from the one before it, in the order they happened
+    pub fn offset_changes(&self) -> Result<Vec<OffsetChange>> {
+        let mut recorded = vec![];
+        for entry in &self.entries {
+            recorded.push(entry.recorded_check_in());
+            recorded.push(entry.recorded_check_out()?);
+        }
+        if let Some(session) = &self.current_session {
+            recorded.push(session.recorded_check_in());
         }
+
+        Ok(recorded
+            .windows(2)
+            .filter(|pair| pair[0].offset() != pair[1].offset())
+            .map(|pair| OffsetChange {
+                date: pair[1].date_naive(),
+                from: *pair[0].offset(),
+                to: *pair[1].offset(),
+            })
+            .collect())
+    }
+
+    fn retain_by_labels(self, predicate: impl Fn(&Labels) -> bool) -> Self {
+        let Self {
+            entries,
+            current_session,
+        } = self;
+        Self {
+            entries: entries
+                .into_iter()
+                .filter(|entry| predicate(&entry.labels))
+                .collect(),
+            current_session: current_session.filter(|cs| predicate(&cs.labels)),
+        }
+    }
+
+    /// Keeps only the entries, and current session, whose labels match `filter`
+    pub fn filtered(self, filter: &Filter) -> Self {
+        self.retain_by_labels(|labels| filter.matches(labels))
+    }
+
+    /// Keeps only the entries, and current session, assigned to exactly `project`
+    pub fn for_project(self, project: Option<&str>) -> Self {
+        self.retain_by_labels(|labels| labels.project.as_deref() == project)
+    }
+
+    /// Every distinct project in the record, including `None` if some sessions have no project
+    pub fn projects(&self) -> std::collections::BTreeSet<Option<&str>> {
+        self.entries
+            .iter()
+            .map(|entry| &entry.labels)
+            .chain(self.current_session.iter().map(|cs| &cs.labels))
+            .map(|labels| labels.project.as_deref())
+            .collect()
     }
 
     pub fn clone_last_datetime(&self) -> Result<Option<DateTime<Tz>>> {
         match self.get_latest() {
             Latest::Entry(entry) => Some(entry.get_check_out()).transpose(),
-            Latest::Current(current, _) => Ok(Some(current.clone())),
+            Latest::Current(current) => Ok(Some(current.last_datetime())),
             Latest::None => Ok(None),
         }
     }
 
+    /// Removes the latest time punched, which may be the start or end of a break
     pub fn pop(&mut self) -> Option<(DateTime<Tz>, Option<String>)> {
-        self.current_session.take().or_else(|| {
-            self.entries.pop().map(|entry| {
-                let out_comment = entry.out_comment.clone();
-                let in_comment = entry.in_comment.clone();
-                let (start, end) = DateTimeRange::from(entry).into_bounds();
-                // Something about mutating self inside this closure feels very wrong...
-                self.current_session = Some((start, in_comment));
-                (end, out_comment)
+        if let Some(session) = &mut self.current_session {
+            if let Some(pause) = session.breaks.last_mut() {
+                if let Some(end) = pause.end.take() {
+                    return Some((end, None));
+                }
+                return session
+                    .breaks
+                    .pop()
+                    .map(|pause| (pause.start, pause.comment));
+            }
+        }
+
+        self.current_session
+            .take()
+            .map(|cs| (cs.check_in, cs.in_comment))
+            .or_else(|| {
+                self.entries.pop().map(|entry| {
+                    let out_comment = entry.out_comment.clone();
+                    let in_comment = entry.in_comment.clone();
+                    let labels = entry.labels.clone();
+                    let recorded_offset = entry.recorded_offsets.check_in;
+                    let breaks = entry.breaks.clone();
+                    let (check_in, end) = DateTimeRange::from(entry).into_bounds();
+                    // Something about mutating self inside this closure feels very wrong...
+                    self.current_session = Some(Session {
+                        check_in,
+                        in_comment,
+                        labels,
+                        recorded_offset,
+                        breaks,
+                    });
+                    (end, out_comment)
+                })
             })
-        })
     }
 
-    pub fn get_current_session(&self) -> Option<&(DateTime<Tz>, Option<String>)> {
+    pub fn get_current_session(&self) -> Option<&Session<Tz>> {
         self.current_session.as_ref()
     }
 
@@ -220,13 +479,71 @@ impl<Tz: TimeZone> Record<Tz> {
         &self.entries
     }
 
+    /// A record of just the entries in `range`, without the current session
+    pub fn sub_record(&self, range: std::ops::Range<usize>) -> Self {
+        Self {
+            entries: self.entries[range].to_vec(),
+            current_session: None,
+        }
+    }
+
+    /// Inserts `entry` among the existing entries, keeping them in chronological order
+    pub fn insert(&mut self, entry: Entry<Tz>) {
+        let index = self
+            .entries
+            .partition_point(|existing| existing.check_in <= entry.check_in);
+        self.entries.insert(index, entry);
+    }
+
+    /// Finds an existing entry sharing time with `entry`, or the current session if `entry` ends after it started
+    pub fn find_overlap(&self, entry: &Entry<Tz>) -> Result<Option<Item<Tz>>> {
+        for existing in &self.entries {
+            if existing.overlaps(entry)? {
+                return Ok(Some(Item::Entry(existing.clone())));
+            }
+        }
+
+        match &self.current_session {
+            Some(current_session) if entry.get_check_out()? > current_session.check_in => {
+                Ok(Some(Item::CurrentSession(current_session.clone())))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Inserts each of `entries` that doesn't duplicate or overlap the record, reporting the ones that do
+    pub fn merge(&mut self, mut entries: Vec<Entry<Tz>>) -> Result<Merged<Tz>> {
+        entries.sort_by(|a, b| a.check_in.cmp(&b.check_in));
+
+        let mut merged = Merged {
+            added: 0,
+            duplicates: vec![],
+            overlaps: vec![],
+        };
+
+        for entry in entries {
+            if self
+                .entries
+                .iter()
+                .any(|existing| existing.same_time_as(&entry))
+            {
+                merged.duplicates.push(entry);
+            } else if self.find_overlap(&entry)?.is_some() {
+                merged.overlaps.push(entry);
+            } else {
+                self.insert(entry);
+                merged.added += 1;
+            }
+        }
+
+        Ok(merged)
+    }
+
     pub fn get_latest(&self) -> Latest<'_, Tz> {
         match (&self.current_session, self.entries.last()) {
             (None, None) => Latest::None,
             (None, Some(last_entry)) => Latest::Entry(last_entry),
-            (Some((current_session, in_comment)), _) => {
-                Latest::Current(current_session, in_comment.as_deref())
-            }
+            (Some(current_session), _) => Latest::Current(current_session),
         }
     }
 
@@ -236,60 +553,86 @@ impl<Tz: TimeZone> Record<Tz> {
     {
         let mut buf = String::new();
         for entry @ Entry {
-            check_in,
-            work_time_millis: _,
             in_comment,
             out_comment,
+            labels,
+            ..
         } in &self.entries
         {
-            write!(buf, "{:<32}", check_in.to_rfc3339())?;
+            write!(buf, "{:<32}", entry.recorded_check_in().to_rfc3339())?;
             if let Some(comment) = in_comment {
                 write!(buf, " {comment}")?;
             }
             writeln!(buf)?;
-            write!(buf, "{:<32}", entry.get_check_out()?.to_rfc3339())?;
+            write!(buf, "{:<32}", entry.recorded_check_out()?.to_rfc3339())?;
             if let Some(comment) = out_comment {
                 write!(buf, " {comment}")?;
             }
             writeln!(buf)?;
+            labels.write_annotations(&mut buf)?;
+            breaks::write_annotations(&entry.breaks, &entry.recorded_offsets.check_in, &mut buf)?;
             writeln!(buf)?;
         }
 
-        match self.current_session.clone() {
-            Some((current_session, Some(in_comment))) => {
-                writeln!(buf, "{:<32} {}", current_session.to_rfc3339(), in_comment)?;
-            }
-            Some((current_session, None)) => {
-                writeln!(buf, "{:<32}", current_session.to_rfc3339())?;
+        if let Some(
+            current_session @ Session {
+                in_comment, labels, ..
+            },
+        ) = &self.current_session
+        {
+            write!(
+                buf,
+                "{:<32}",
+                current_session.recorded_check_in().to_rfc3339()
+            )?;
+            if let Some(comment) = in_comment {
+                write!(buf, " {comment}")?;
             }
-            _ => (),
+            writeln!(buf)?;
+            labels.write_annotations(&mut buf)?;
+            breaks::write_annotations(
+                &current_session.breaks,
+                &current_session.recorded_offset,
+                &mut buf,
+            )?;
         }
 
         Ok(buf)
     }
 }
 
+pub struct Merged<Tz: TimeZone> {
+    pub added: usize,
+    pub duplicates: Vec<Entry<Tz>>,
+    pub overlaps: Vec<Entry<Tz>>,
+}
+
 pub struct Iterator<Tz: TimeZone> {
     entries: std::iter::Rev<std::vec::IntoIter<Entry<Tz>>>,
-    current_session: std::option::IntoIter<(DateTime<Tz>, Option<String>)>,
+    current_session: std::option::IntoIter<Session<Tz>>,
 }
 
 #[cfg_attr(test, derive(Debug, PartialEq, Eq))]
 pub enum Item<Tz: TimeZone> {
     Entry(Entry<Tz>),
-    CurrentSession(DateTime<Tz>, Option<String>),
+    CurrentSession(Session<Tz>),
 }
 
 impl<Tz: TimeZone> Item<Tz> {
+    pub fn check_in(&self) -> &DateTime<Tz> {
+        match self {
+            Item::Entry(entry) => &entry.check_in,
+            Item::CurrentSession(current_session) => &current_session.check_in,
+        }
+    }
+
     pub fn into_entry<F>(self, end: F) -> Result<Entry<Tz>>
     where
         F: FnOnce() -> DateTime<Tz>,
     {
         match self {
             Item::Entry(entry) => Ok(entry),
-            Item::CurrentSession(current_session, in_comment) => {
-                Entry::try_new(current_session, end(), in_comment, None)
-            }
+            Item::CurrentSession(current_session) => current_session.into_entry(end(), None),
         }
     }
 }
@@ -298,13 +641,10 @@ impl<Tz: TimeZone> std::iter::Iterator for Iterator<Tz> {
     type Item = Item<Tz>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.entries.next_back().map(Item::Entry).or_else(|| {
-            self.current_session
-                .next()
-                .map(|(current_session, in_comment)| {
-                    Item::CurrentSession(current_session, in_comment)
-                })
-        })
+        self.entries
+            .next_back()
+            .map(Item::Entry)
+            .or_else(|| self.current_session.next().map(Item::CurrentSession))
     }
 }
 
@@ -312,7 +652,7 @@ impl<Tz: TimeZone> DoubleEndedIterator for Iterator<Tz> {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.current_session
             .next_back()
-            .map(|(current_session, in_comment)| Item::CurrentSession(current_session, in_comment))
+            .map(Item::CurrentSession)
             .or_else(|| self.entries.next().map(Item::Entry))
     }
 }
@@ -331,45 +671,72 @@ impl<Tz: TimeZone> IntoIterator for Record<Tz> {
 }
 
 impl<Tz: ContextTimeZone> Record<Tz> {
+    /// Flags the days in `offset_changes` after their line
     pub fn paint_calendar(
         &self,
         ctx: &context::Context<Tz>,
         range: RangeInclusive<NaiveDate>,
         width: usize,
+        offset_changes: &[OffsetChange],
     ) -> Result<()> {
-        display::paint_day_range(ctx, self, range, width)?;
+        display::paint_day_range(ctx, self, range, width, offset_changes)?;
         Ok(())
     }
 
+    /// The calendar as a standalone HTML page
+    pub fn calendar_html(
+        &self,
+        ctx: &context::Context<Tz>,
+        range: RangeInclusive<NaiveDate>,
+        offset_changes: &[OffsetChange],
+    ) -> Result<String> {
+        display::html::calendar_page(ctx, self, range, offset_changes)
+    }
+
     pub fn days_time(self, ctx: &context::Context<Tz>, day: NaiveDate) -> Result<Duration> {
-        let datetime_ranges = self.try_into_cropped_datetime_ranges(
-            ctx,
-            day.into_day_start(ctx)?,
-            day.into_day_end(ctx)?,
-        )?;
+        Ok(self.days_ranges(ctx, day)?.into_iter().sum())
+    }
+
+    /// The time on `day` from the first work to the last that wasn't worked, whether on a break or between sessions
+    pub fn days_rest(self, ctx: &context::Context<Tz>, day: NaiveDate) -> Result<Duration> {
+        let ranges = self.days_ranges(ctx, day)?;
+        let (Some(first), Some(last)) = (
+            ranges.iter().map(|dtr| *dtr.start()).min(),
+            ranges.iter().map(|dtr| *dtr.end()).max(),
+        ) else {
+            return Ok(Duration::zero());
+        };
+        let worked: Duration = ranges.into_iter().sum();
+        Ok(last - first - worked)
+    }
 
-        Ok(datetime_ranges.into_iter().sum())
+    pub fn days_ranges(
+        self,
+        ctx: &context::Context<Tz>,
+        day: NaiveDate,
+    ) -> Result<Vec<DateTimeRange<Tz>>> {
+        self.try_into_cropped_datetime_ranges(ctx, day.into_day_start(ctx)?, day.into_day_end(ctx)?)
     }
 
-    pub fn todays_time(self, ctx: &context::Context<Tz>) -> Result<Duration> {
-        let now = Local::now();
+    pub fn todays_ranges(self, ctx: &context::Context<Tz>) -> Result<Vec<DateTimeRange<Tz>>> {
+        let now = ctx.timezone.now();
         let today = now.date_naive();
 
-        let mut datetime_ranges_today = self
-            .into_iter()
-            .map(|item| {
-                item.into_entry(|| ctx.timezone.now())
-                    .map(DateTimeRange::from)
-            })
-            .rev()
-            .take_while(|dtr| dtr.iter().any(|dtr| dtr.end().date_naive() == today))
-            .collect::<Result<Vec<_>>>()?;
+        let mut datetime_ranges_today = vec![];
+        'items: for item in self.into_iter().rev() {
+            let work_ranges = item.into_entry(|| ctx.timezone.now())?.into_work_ranges()?;
+            for dtr in work_ranges.into_iter().rev() {
+                if dtr.end().date_naive() != today {
+                    break 'items;
+                }
+                datetime_ranges_today.push(dtr);
+            }
+        }
 
-        let Some((first_check_in, first_check_out)) =
-            datetime_ranges_today.pop().map(DateTimeRange::into_bounds)
-        else {
-            return Ok(Duration::zero());
+        let Some(first) = datetime_ranges_today.pop() else {
+            return Ok(vec![]);
         };
+        let (first_check_in, first_check_out) = first.clone().into_bounds();
 
         let first_check_in = if first_check_in.date_naive() == today {
             first_check_in
@@ -377,28 +744,60 @@ impl<Tz: ContextTimeZone> Record<Tz> {
             today.into_day_start(ctx)?
         };
 
-        datetime_ranges_today.push(DateTimeRange::new(first_check_in, first_check_out)?);
+        datetime_ranges_today.push(first.cropped(first_check_in, first_check_out)?);
 
-        Ok(datetime_ranges_today.into_iter().sum())
+        Ok(datetime_ranges_today)
     }
 
-    pub fn total_time(self, ctx: &context::Context<Tz>) -> Result<Duration> {
-        let datetime_ranges = self
-            .into_iter()
-            .map(|entry| {
-                entry
-                    .into_entry(|| ctx.timezone.now())
-                    .map(DateTimeRange::from)
-            })
-            .collect::<Result<Vec<_>>>()?;
+    pub fn total_ranges(self, ctx: &context::Context<Tz>) -> Result<Vec<DateTimeRange<Tz>>> {
+        let mut datetime_ranges = vec![];
+        for item in self {
+            datetime_ranges.extend(item.into_entry(|| ctx.timezone.now())?.into_work_ranges()?);
+        }
+        Ok(datetime_ranges)
+    }
 
-        Ok(datetime_ranges.into_iter().sum())
+    pub fn days_break_ranges(
+        self,
+        ctx: &context::Context<Tz>,
+        day: NaiveDate,
+    ) -> Result<Vec<DateTimeRange<Tz>>> {
+        self.try_into_cropped_break_ranges(ctx, day.into_day_start(ctx)?, day.into_day_end(ctx)?)
     }
 
+    /// The breaks taken between `start` and `end`, cut to fit
+    pub fn try_into_cropped_break_ranges(
+        self,
+        ctx: &Context<Tz>,
+        start: DateTime<Tz>,
+        end: DateTime<Tz>,
+    ) -> Result<Vec<DateTimeRange<Tz>>> {
+        let mut break_ranges = vec![];
+        for item in self {
+            if *item.check_in() >= end {
+                break;
+            }
+            let entry = item.into_entry(|| ctx.timezone.now().min(end))?;
+            for (break_start, break_end) in entry
+                .break_ranges()?
+                .into_iter()
+                .map(DateTimeRange::into_bounds)
+            {
+                let (break_start, break_end) = (break_start.max(start), break_end.min(end));
+                if break_start < break_end {
+                    break_ranges.push(DateTimeRange::new(break_start, break_end)?);
+                }
+            }
+        }
+        Ok(break_ranges)
+    }
+
+    /// Time worked in the current session so far, not counting breaks, as a negative duration
     pub fn current_session_time(&self, ctx: &Context<Tz>) -> Option<Duration> {
+        let now = ctx.timezone.now();
         self.current_session
             .as_ref()
-            .map(|(sesh, _)| sesh.signed_duration_since(ctx.timezone.now()))
+            .map(|sesh| sesh.check_in.signed_duration_since(now) + sesh.get_break_duration(&now))
     }
 
     pub fn try_into_cropped_datetime_ranges(
@@ -412,30 +811,29 @@ impl<Tz: ContextTimeZone> Record<Tz> {
             return Err(error::Main::RangeStartPosition);
         }
 
-        let mut datetime_ranges =
-            self.into_iter()
-                .map(|item| {
-                    item.into_entry(|| ctx.timezone.now().min(end))
-                        .map(DateTimeRange::from)
-                })
-                .filter(|entry| {
-                    entry.iter().map(|dtr| dtr.clone().into_bounds()).any(
-                        |(check_in, check_out)| {
-                            (start <= check_in && check_in < end)
-                                || (start <= check_out && check_out < end)
-                        },
-                    )
-                })
-                .collect::<Result<VecDeque<_>>>()?;
+        let mut datetime_ranges = VecDeque::new();
+        for item in self {
+            // An open session starting after the range would otherwise be clocked out before it began
+            if *item.check_in() >= end {
+                continue;
+            }
+            let work_ranges = item
+                .into_entry(|| ctx.timezone.now().min(end))?
+                .into_work_ranges()?;
+            datetime_ranges.extend(work_ranges.into_iter().filter(|dtr| {
+                let (check_in, check_out) = (*dtr.start(), *dtr.end());
+                (start <= check_in && check_in < end) || (start <= check_out && check_out < end)
+            }));
+        }
 
         if datetime_ranges.is_empty() {
             return Ok(vec![]);
         }
 
-        let (first_check_in, first_check_out) = datetime_ranges
+        let first = datetime_ranges
             .pop_front()
-            .expect("datetime_ranges must be confirmed to have at least one element")
-            .into_bounds();
+            .expect("datetime_ranges must be confirmed to have at least one element");
+        let (first_check_in, first_check_out) = first.clone().into_bounds();
 
         let first_check_in = if first_check_in < start {
             start
@@ -443,12 +841,12 @@ impl<Tz: ContextTimeZone> Record<Tz> {
             first_check_in
         };
 
-        datetime_ranges.push_front(DateTimeRange::new(first_check_in, first_check_out)?);
+        datetime_ranges.push_front(first.cropped(first_check_in, first_check_out)?);
 
-        let (last_check_in, last_check_out) = datetime_ranges
+        let last = datetime_ranges
             .pop_back()
-            .expect("datetime_ranges must be confirmed to have at least one element")
-            .into_bounds();
+            .expect("datetime_ranges must be confirmed to have at least one element");
+        let (last_check_in, last_check_out) = last.clone().into_bounds();
 
         let last_check_out = if last_check_out > end {
             end
@@ -456,113 +854,192 @@ impl<Tz: ContextTimeZone> Record<Tz> {
             last_check_out
         };
 
-        datetime_ranges.push_back(DateTimeRange::new(last_check_in, last_check_out)?);
+        datetime_ranges.push_back(last.cropped(last_check_in, last_check_out)?);
 
         Ok(datetime_ranges.into())
     }
 }
 
+/// `at` as long as it isn't in the future
+fn not_in_future<Tz: ContextTimeZone>(at: &DateTime<Tz>) -> Result<DateTime<Utc>> {
+    let utc = at.with_timezone(&Utc);
+    if utc > Utc::now() {
+        return Err(error::Main::TimeInFuture(local_display(at, &at.timezone())));
+    }
+    Ok(utc)
+}
+
 impl Record<Utc> {
-    pub fn clock_in(&mut self, comment: Option<String>) -> Result<DateTime<Utc>> {
+    /// Starts a session at `at`, recording its offset. Errors show times in the timezone of `at`.
+    pub fn clock_in<Tz: ContextTimeZone>(
+        &mut self,
+        comment: Option<String>,
+        labels: Labels,
+        at: DateTime<Tz>,
+    ) -> Result<DateTime<Utc>> {
+        let timezone = at.timezone();
         if self.current_session.is_some() {
             return Err(error::Main::AlreadyClockedIn);
         };
 
-        let now = Utc::now();
+        let check_in = not_in_future(&at)?;
 
-        self.current_session = Some((now, comment));
+        if let Some(last_entry) = self.entries.last() {
+            let last_check_out = last_entry.get_check_out()?;
+            if check_in < last_check_out {
+                return Err(error::Main::BeforeLastCheckOut {
+                    time: local_display(&check_in, &timezone),
+                    check_out: local_display(&last_check_out, &timezone),
+                });
+            }
+        }
+
+        self.current_session = Some(Session {
+            check_in,
+            in_comment: comment,
+            labels,
+            recorded_offset: *at.fixed_offset().offset(),
+            breaks: vec![],
+        });
+
+        Ok(check_in)
+    }
+
+    /// Replaces the entries in `range` with `entries`, leaving the record untouched if any of them would overlap the rest of it
+    pub fn replace_entries<Tz: ContextTimeZone>(
+        &mut self,
+        range: std::ops::Range<usize>,
+        entries: Vec<Entry<Utc>>,
+        timezone: &Tz,
+    ) -> Result<()> {
+        let removed = self.entries.drain(range.clone()).collect::<Vec<_>>();
+
+        let overlap = entries
+            .iter()
+            .map(|entry| self.find_overlap(entry))
+            .find_map(Result::transpose);
+        if let Some(overlap) = overlap {
+            self.entries.splice(range.start..range.start, removed);
+            return Err(match overlap {
+                Ok(existing) => {
+                    error::Main::OverlapsSession(local_display(existing.check_in(), timezone))
+                }
+                Err(err) => err,
+            });
+        }
 
-        Ok(now)
+        for entry in entries {
+            self.insert(entry);
+        }
+
+        Ok(())
     }
 
-    pub fn clock_out(&mut self, out_comment: Option<String>) -> Result<(DateTime<Utc>, Duration)> {
-        let Some((current_session, in_comment)) = self.current_session.as_ref() else {
+    /// Ends the current session at `at`, recording its offset. Errors show times in the timezone of `at`.
+    pub fn clock_out<Tz: ContextTimeZone>(
+        &mut self,
+        out_comment: Option<String>,
+        at: DateTime<Tz>,
+    ) -> Result<(DateTime<Utc>, Duration)> {
+        let timezone = at.timezone();
+        let Some(current_session) = self.current_session.clone() else {
             return Err(error::Main::NotClockedIn);
         };
 
-        let now = Utc::now();
-        let since = current_session.signed_duration_since(now);
+        let check_out = not_in_future(&at)?;
+        if check_out < current_session.check_in {
+            return Err(error::Main::BeforeCheckIn {
+                time: local_display(&check_out, &timezone),
+                check_in: local_display(&current_session.check_in, &timezone),
+            });
+        }
+        if let Some(last_break) = current_session.breaks.last() {
+            if check_out < last_break.start {
+                return Err(error::Main::BeforeBreakStart {
+                    time: local_display(&check_out, &timezone),
+                    start: local_display(&last_break.start, &timezone),
+                });
+            }
+        }
+
+        let since = current_session.check_in.signed_duration_since(check_out);
 
-        self.entries.push(Entry::try_new(
-            *current_session,
-            Utc::now(),
-            in_comment.clone(),
-            out_comment,
-        )?);
+        let mut entry = current_session.into_entry(check_out, out_comment)?;
+        entry.recorded_offsets.check_out = *at.fixed_offset().offset();
+        self.entries.push(entry);
 
         self.current_session = None;
 
-        Ok((now, since))
+        Ok((check_out, since))
     }
 
-    pub fn load() -> Result<Option<Self>> {
-        if !std::path::Path::new(".punch_clock").exists() {
-            return Ok(None);
+    /// Starts a break in the current session at `at`
+    pub fn take_break<Tz: ContextTimeZone>(
+        &mut self,
+        comment: Option<String>,
+        at: DateTime<Tz>,
+    ) -> Result<DateTime<Utc>> {
+        let timezone = at.timezone();
+        let Some(current_session) = &mut self.current_session else {
+            return Err(error::Main::NotClockedIn);
+        };
+        if let Some(open_break) = current_session.open_break() {
+            return Err(error::Main::AlreadyOnBreak(local_display(
+                &open_break.start,
+                &timezone,
+            )));
         }
 
-        let record: Record<FixedOffset> = fs::read_to_string(".punch_clock/record")?
-            .as_str()
-            .try_into()?;
-
-        let record = record.with_timezone(&Utc);
-
-        Ok(Some(record))
-    }
-
-    pub fn init() -> Result<()> {
-        if Self::load()?.is_some() {
-            return Err(error::Main::AlreadyInitialized);
+        let start = not_in_future(&at)?;
+        if start < current_session.check_in {
+            return Err(error::Main::BeforeCheckIn {
+                time: local_display(&start, &timezone),
+                check_in: local_display(&current_session.check_in, &timezone),
+            });
+        }
+        if let Some(last_end) = current_session.breaks.last().and_then(|last| last.end) {
+            if start < last_end {
+                return Err(error::Main::BeforeLastBreak {
+                    time: local_display(&start, &timezone),
+                    end: local_display(&last_end, &timezone),
+                });
+            }
         }
 
-        fs::create_dir(".punch_clock")?;
-        fs::write(".punch_clock/record", "")?;
+        current_session.breaks.push(Break {
+            start,
+            end: None,
+            comment,
+        });
 
-        Ok(())
+        Ok(start)
     }
-}
-
-impl TryFrom<&str> for Record<FixedOffset> {
-    type Error = error::Main;
 
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let mut current_session = None;
-
-        let mut paragraphs = split_sparse_tokens_str(value, "\n\n");
-
-        let last_paragraph = paragraphs.pop();
-
-        let mut entries = paragraphs
-            .into_iter()
-            .map(Entry::try_from)
-            .collect::<Result<Vec<_>>>()?;
+    /// Ends the current break at `at`, returning that and how long the break lasted
+    pub fn resume<Tz: ContextTimeZone>(
+        &mut self,
+        at: DateTime<Tz>,
+    ) -> Result<(DateTime<Utc>, Duration)> {
+        let timezone = at.timezone();
+        let Some(open_break) = self
+            .current_session
+            .as_mut()
+            .and_then(|session| session.breaks.last_mut())
+            .filter(|pause| pause.end.is_none())
+        else {
+            return Err(error::Main::NotOnBreak);
+        };
 
-        if let Some(last_paragraph) = last_paragraph {
-            match split_sparse_tokens(last_paragraph, '\n').as_slice() {
-                [check_in_line, check_out_line] => {
-                    entries.push(Entry::from_lines(check_in_line, check_out_line)?);
-                }
-                [session_line] => match split_sparse_tokens(session_line, ' ').as_slice() {
-                    [check_in] => {
-                        current_session = Some((DateTime::parse_from_rfc3339(check_in)?, None));
-                    }
-                    [check_in, in_comment @ ..] => {
-                        current_session = Some((
-                            DateTime::parse_from_rfc3339(check_in)?,
-                            Some(in_comment.join(" ")),
-                        ));
-                    }
-                    _ => return Err(error::Main::EntryIncorrectNumberOfTokens),
-                },
-                _ => {
-                    return Err(error::Main::EntryIncorrectNumberOfLines);
-                }
-            }
+        let end = not_in_future(&at)?;
+        if end < open_break.start {
+            return Err(error::Main::BeforeBreakStart {
+                time: local_display(&end, &timezone),
+                start: local_display(&open_break.start, &timezone),
+            });
         }
+        open_break.end = Some(end);
 
-        Ok(Self {
-            entries,
-            current_session,
-        })
+        Ok((end, end - open_break.start))
     }
 }
 
@@ -573,14 +1050,25 @@ mod test {
 
     use super::{display::paint_day_range, Record};
     use crate::{
-        app::context,
-        record::{self, Entry},
+        app::{config::Config, context},
+        record::{self, Entry, Labels, RecordedOffsets, Session},
     };
 
     fn date_md(month: u32, day: u32) -> chrono::NaiveDate {
         chrono::NaiveDate::from_ymd_opt(2023, month, day).unwrap()
     }
 
+    fn utc_offset() -> FixedOffset {
+        FixedOffset::east_opt(0).unwrap()
+    }
+
+    fn utc_offsets() -> RecordedOffsets {
+        RecordedOffsets {
+            check_in: utc_offset(),
+            check_out: utc_offset(),
+        }
+    }
+
     fn datetime_hm(hour: u32, min: u32) -> DateTime<FixedOffset> {
         FixedOffset::west_opt(0)
             .unwrap()
@@ -615,14 +1103,26 @@ mod test {
                     work_time_millis: 3_600_000,
                     in_comment: None,
                     out_comment: None,
+                    labels: Labels::default(),
+                    recorded_offsets: utc_offsets(),
+                    breaks: vec![],
                 }),
                 record::Item::Entry(Entry {
                     check_in: datetime_hm(2, 0),
                     work_time_millis: 3_600_000,
                     in_comment: None,
                     out_comment: Some("This is a comment!".to_owned()),
+                    labels: Labels::default(),
+                    recorded_offsets: utc_offsets(),
+                    breaks: vec![],
+                }),
+                record::Item::CurrentSession(Session {
+                    check_in: datetime_hm(4, 0),
+                    in_comment: None,
+                    labels: Labels::default(),
+                    recorded_offset: utc_offset(),
+                    breaks: vec![],
                 }),
-                record::Item::CurrentSession(datetime_hm(4, 0), None),
             ],
             rec_vec
         );
@@ -635,18 +1135,30 @@ mod test {
 
         assert_eq!(
             vec![
-                record::Item::CurrentSession(datetime_hm(4, 0), None),
+                record::Item::CurrentSession(Session {
+                    check_in: datetime_hm(4, 0),
+                    in_comment: None,
+                    labels: Labels::default(),
+                    recorded_offset: utc_offset(),
+                    breaks: vec![],
+                }),
                 record::Item::Entry(Entry {
                     check_in: datetime_hm(2, 0),
                     work_time_millis: 3_600_000,
                     in_comment: None,
                     out_comment: Some("This is a comment!".to_owned()),
+                    labels: Labels::default(),
+                    recorded_offsets: utc_offsets(),
+                    breaks: vec![],
                 }),
                 record::Item::Entry(Entry {
                     check_in: datetime_hm(0, 0),
                     work_time_millis: 3_600_000,
                     in_comment: None,
                     out_comment: None,
+                    labels: Labels::default(),
+                    recorded_offsets: utc_offsets(),
+                    breaks: vec![],
                 }),
             ],
             rec_vec
@@ -656,39 +1168,42 @@ mod test {
     #[test]
     fn range_end_index_x_out_of_range_for_slice_of_length_y() {
         let ctx = context::Context {
-            editor_path: String::new(),
+            editor_path: None,
             timezone: FixedOffset::east_opt(0).unwrap(),
             skip_hooks: Default::default(),
+            config: Config::default(),
         };
         let rec_file = "2023-07-10T05:05:42.372091+00:00 2023-07-10T09:38:44.320091+00:00
 2023-07-10T20:00:00+00:00        2023-07-10T22:13:34.369+00:00";
         let rec = Record::try_from(rec_file)
             .unwrap()
             .with_timezone(&ctx.timezone);
-        paint_day_range(&ctx, &rec, date_md(7, 9)..=date_md(7, 10), 48).unwrap();
+        paint_day_range(&ctx, &rec, date_md(7, 9)..=date_md(7, 10), 48, &[]).unwrap();
     }
 
     #[test]
     fn range_end_index_x_out_of_range_for_slice_of_length_y_2() {
         let ctx = context::Context {
-            editor_path: String::new(),
+            editor_path: None,
             timezone: FixedOffset::east_opt(0).unwrap(),
             skip_hooks: Default::default(),
+            config: Config::default(),
         };
         let rec_file = "2023-06-04T21:08:34.790590+00:00 2023-06-04T22:32:47.660590+00:00
 2023-06-05T04:30:04.199633+00:00 2023-06-05T07:18:50.734633+00:00";
         let rec = Record::try_from(rec_file)
             .unwrap()
             .with_timezone(&ctx.timezone);
-        paint_day_range(&ctx, &rec, date_md(6, 4)..=date_md(6, 5), 48).unwrap();
+        paint_day_range(&ctx, &rec, date_md(6, 4)..=date_md(6, 5), 48, &[]).unwrap();
     }
 
     #[test]
     fn range_end_index_x_out_of_range_for_slice_of_length_y_3() {
         let ctx = context::Context {
-            editor_path: String::new(),
+            editor_path: None,
             timezone: FixedOffset::east_opt(12 * 3600).unwrap(),
             skip_hooks: Default::default(),
+            config: Config::default(),
         };
         let rec_file = "2023-06-30T04:30:00.893153+00:00
 2023-06-30T07:15:07.931153+00:00
@@ -726,7 +1241,7 @@ mod test {
         let rec = Record::try_from(rec_file)
             .unwrap()
             .with_timezone(&ctx.timezone);
-        paint_day_range(&ctx, &rec, date_md(7, 10)..=date_md(7, 12), 24).unwrap();
+        paint_day_range(&ctx, &rec, date_md(7, 10)..=date_md(7, 12), 24, &[]).unwrap();
     }
 
     #[test]
@@ -746,10 +1261,13 @@ mod test {
         let rec_vec = rec.into_iter().rev().collect::<Vec<_>>();
 
         assert_eq!(
-            vec![record::Item::CurrentSession(
-                datetime_hm(4, 0),
-                Some("Blah blah blah".to_string())
-            ),],
+            vec![record::Item::CurrentSession(Session {
+                check_in: datetime_hm(4, 0),
+                in_comment: Some("Blah blah blah".to_string()),
+                labels: Labels::default(),
+                recorded_offset: utc_offset(),
+                breaks: vec![],
+            })],
             rec_vec
         );
     }
@@ -768,7 +1286,10 @@ mod test {
                 check_in: datetime_hm(4, 0),
                 work_time_millis: 3_600_000,
                 in_comment: None,
-                out_comment: Some("Blah blah blah".to_string())
+                out_comment: Some("Blah blah blah".to_string()),
+                labels: Labels::default(),
+                recorded_offsets: utc_offsets(),
+                breaks: vec![],
             })],
             rec_vec
         );
@@ -789,8 +1310,305 @@ mod test {
                 work_time_millis: 3_600_000,
                 in_comment: Some("Blah blah blah".to_string()),
                 out_comment: None,
+                labels: Labels::default(),
+                recorded_offsets: utc_offsets(),
+                breaks: vec![],
             })],
             rec_vec
         );
     }
+
+    const LABELLED_RECORD_STR: &str = "2023-01-01T00:00:00+00:00        Starting on the widget
+2023-01-01T01:00:00+00:00       
+@project acme
+@tags review widgets
+
+2023-01-01T04:00:00+00:00       
+@tags review
+";
+
+    #[test]
+    fn labels_read_write_read_integrity() {
+        let rec = Record::try_from(LABELLED_RECORD_STR).unwrap();
+        let written = rec.serialize().unwrap();
+
+        assert_eq!(LABELLED_RECORD_STR, &written);
+    }
+
+    #[test]
+    fn labels_filter() {
+        let rec = Record::try_from(LABELLED_RECORD_STR).unwrap();
+        let filter = record::Filter {
+            project: Some("acme".to_owned()),
+            tags: vec!["review".to_owned()],
+        };
+        let rec_vec = rec.filtered(&filter).into_iter().collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![record::Item::Entry(Entry {
+                check_in: datetime_hm(0, 0),
+                work_time_millis: 3_600_000,
+                in_comment: Some("Starting on the widget".to_owned()),
+                out_comment: None,
+                labels: Labels::try_new(
+                    Some("acme".to_owned()),
+                    vec!["review".to_owned(), "widgets".to_owned()]
+                )
+                .unwrap(),
+                recorded_offsets: utc_offsets(),
+                breaks: vec![],
+            })],
+            rec_vec
+        );
+    }
+
+    #[test]
+    fn merge() {
+        let mut rec = get_record();
+        let merged = rec
+            .merge(vec![
+                Entry::try_new(datetime_hm(1, 0), datetime_hm(1, 30), None, None).unwrap(),
+                Entry::try_new(datetime_hm(0, 0), datetime_hm(1, 0), None, None).unwrap(),
+                Entry::try_new(datetime_hm(2, 30), datetime_hm(3, 30), None, None).unwrap(),
+                Entry::try_new(datetime_hm(3, 30), datetime_hm(4, 30), None, None).unwrap(),
+            ])
+            .unwrap();
+
+        assert_eq!(1, merged.added);
+        assert_eq!(1, merged.duplicates.len());
+        assert_eq!(2, merged.overlaps.len());
+        assert_eq!(
+            vec![datetime_hm(0, 0), datetime_hm(1, 0), datetime_hm(2, 0)],
+            rec.get_entries()
+                .iter()
+                .map(|entry| entry.check_in)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn clock_in_and_out_at() {
+        let utc = |hour, min| datetime_hm(hour, min).with_timezone(&chrono::Utc);
+        let mut rec = get_record().with_timezone(&chrono::Utc);
+        rec.pop();
+
+        assert!(matches!(
+            rec.clock_in(None, Labels::default(), datetime_hm(2, 30)),
+            Err(crate::error::Main::BeforeLastCheckOut { .. })
+        ));
+        assert!(matches!(
+            rec.clock_in(
+                None,
+                Labels::default(),
+                (chrono::Utc::now() + chrono::Duration::minutes(5)).fixed_offset()
+            ),
+            Err(crate::error::Main::TimeInFuture(_))
+        ));
+
+        rec.clock_in(None, Labels::default(), datetime_hm(3, 0))
+            .unwrap();
+        assert!(matches!(
+            rec.clock_out(None, datetime_hm(2, 59)),
+            Err(crate::error::Main::BeforeCheckIn { .. })
+        ));
+
+        let (_, since) = rec.clock_out(None, datetime_hm(4, 15)).unwrap();
+        assert_eq!(chrono::Duration::minutes(-75), since);
+        assert_eq!(utc(4, 15), rec.get_entries()[2].get_check_out().unwrap());
+    }
+
+    #[test]
+    fn replace_entries() {
+        let utc = |hour, min| datetime_hm(hour, min).with_timezone(&chrono::Utc);
+        let mut rec = get_record().with_timezone(&chrono::Utc);
+
+        let err = rec
+            .replace_entries(
+                0..1,
+                vec![Entry::try_new(utc(0, 0), utc(2, 30), None, None).unwrap()],
+                &FixedOffset::east_opt(3600).unwrap(),
+            )
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            crate::error::Main::OverlapsSession(ref time) if time == "Sun Jan  1 03:00:00 2023"
+        ));
+        assert_eq!(utc(1, 0), rec.get_entries()[0].get_check_out().unwrap());
+
+        rec.replace_entries(
+            0..1,
+            vec![
+                Entry::try_new(utc(0, 0), utc(0, 30), None, None).unwrap(),
+                Entry::try_new(utc(0, 30), utc(1, 30), None, None).unwrap(),
+            ],
+            &FixedOffset::east_opt(0).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            vec![utc(0, 0), utc(0, 30), utc(2, 0)],
+            rec.get_entries()
+                .iter()
+                .map(|entry| entry.check_in)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    const TRAVEL_RECORD_STR: &str = "2023-03-01T09:00:00+01:00       
+2023-03-01T17:00:00+01:00       
+
+2023-03-02T09:00:00-05:00       
+2023-03-02T12:00:00-05:00       
+
+2023-03-03T09:00:00-05:00       
+";
+
+    #[test]
+    fn serialize_keeps_recorded_offsets() {
+        let rec = Record::try_from(TRAVEL_RECORD_STR)
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+
+        assert_eq!(TRAVEL_RECORD_STR, rec.serialize().unwrap());
+    }
+
+    #[test]
+    fn offset_changes() {
+        let rec = Record::try_from(TRAVEL_RECORD_STR)
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+
+        assert_eq!(
+            vec![record::OffsetChange {
+                date: date_md(3, 2),
+                from: FixedOffset::east_opt(3600).unwrap(),
+                to: FixedOffset::west_opt(5 * 3600).unwrap(),
+            }],
+            rec.offset_changes().unwrap()
+        );
+    }
+
+    #[test]
+    fn shifted_to_recorded_keeps_wall_clock_times() {
+        let rec = Record::try_from(TRAVEL_RECORD_STR)
+            .unwrap()
+            .with_timezone(&chrono::Utc)
+            .shifted_to_recorded(&utc_offset())
+            .unwrap();
+
+        assert_eq!(
+            vec!["2023-03-01T09:00:00+00:00", "2023-03-02T09:00:00+00:00"],
+            rec.get_entries()
+                .iter()
+                .map(|entry| entry.check_in.to_rfc3339())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "2023-03-03T09:00:00+00:00",
+            rec.get_current_session().unwrap().check_in.to_rfc3339()
+        );
+    }
+
+    const BREAKS_RECORD_STR: &str = "2023-03-01T09:00:00+01:00       
+2023-03-01T17:00:00+01:00       
+@project acme
+@break 2023-03-01T12:00:00+01:00 2023-03-01T12:45:00+01:00 Lunch
+@break 2023-03-01T15:00:00+01:00 2023-03-01T15:15:00+01:00
+
+2023-03-02T09:00:00+01:00       
+@break 2023-03-02T10:00:00+01:00 Coffee
+";
+
+    #[test]
+    fn breaks_read_write_read_integrity() {
+        let rec = Record::try_from(BREAKS_RECORD_STR)
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+
+        assert_eq!(BREAKS_RECORD_STR, rec.serialize().unwrap());
+    }
+
+    #[test]
+    fn work_ranges_skip_breaks() {
+        let rec = Record::try_from(BREAKS_RECORD_STR).unwrap();
+        let entry = &rec.get_entries()[0];
+        assert_eq!(chrono::Duration::hours(1), entry.get_break_duration());
+
+        let ranges = entry
+            .clone()
+            .into_work_ranges()
+            .unwrap()
+            .into_iter()
+            .map(|dtr| {
+                let (start, end) = dtr.into_bounds();
+                format!("{}-{}", start.format("%R"), end.format("%R"))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["09:00-12:00", "12:45-15:00", "15:15-17:00"], ranges);
+    }
+
+    #[test]
+    fn cropped_ranges_before_open_session() {
+        let tz = chrono::FixedOffset::east_opt(3600).unwrap();
+        let ctx =
+            crate::app::context::Context::init(tz, true, crate::app::config::Config::default());
+        let rec = Record::try_from(BREAKS_RECORD_STR).unwrap();
+        let day = |day| chrono::TimeZone::with_ymd_and_hms(&tz, 2023, 3, day, 0, 0, 0).unwrap();
+
+        let ranges = rec
+            .clone()
+            .try_into_cropped_datetime_ranges(&ctx, day(1), day(2))
+            .unwrap();
+        assert_eq!(3, ranges.len());
+        let breaks = rec
+            .try_into_cropped_break_ranges(&ctx, day(1), day(2))
+            .unwrap();
+        assert_eq!(2, breaks.len());
+    }
+
+    #[test]
+    fn break_and_resume() {
+        let utc = |hour, min| datetime_hm(hour, min).with_timezone(&chrono::Utc);
+        let mut rec = get_record().with_timezone(&chrono::Utc);
+
+        assert!(matches!(
+            rec.take_break(None, datetime_hm(3, 59)),
+            Err(crate::error::Main::BeforeCheckIn { .. })
+        ));
+        rec.take_break(Some("Lunch".to_owned()), datetime_hm(5, 0))
+            .unwrap();
+        assert!(matches!(
+            rec.take_break(None, datetime_hm(5, 30)),
+            Err(crate::error::Main::AlreadyOnBreak(_))
+        ));
+        assert!(matches!(
+            rec.resume(datetime_hm(4, 30)),
+            Err(crate::error::Main::BeforeBreakStart { .. })
+        ));
+
+        let (_, length) = rec.resume(datetime_hm(5, 30)).unwrap();
+        assert_eq!(chrono::Duration::minutes(30), length);
+        assert!(matches!(
+            rec.resume(datetime_hm(5, 45)),
+            Err(crate::error::Main::NotOnBreak)
+        ));
+        assert!(matches!(
+            rec.take_break(None, datetime_hm(5, 15)),
+            Err(crate::error::Main::BeforeLastBreak { .. })
+        ));
+        assert_eq!(Some(utc(5, 30)), rec.clone_last_datetime().unwrap());
+
+        // Undoing takes back the resume, then the break itself
+        assert_eq!(Some((utc(5, 30), None)), rec.pop());
+        assert_eq!(Some((utc(5, 0), Some("Lunch".to_owned()))), rec.pop());
+        assert_eq!(Some((utc(4, 0), None)), rec.pop());
+
+        // Clocking out during a break ends it
+        rec.clock_in(None, Labels::default(), datetime_hm(4, 0))
+            .unwrap();
+        rec.take_break(None, datetime_hm(5, 0)).unwrap();
+        rec.clock_out(None, datetime_hm(5, 20)).unwrap();
+        let entry = rec.get_entries().last().unwrap();
+        assert_eq!(Some(utc(5, 20)), entry.breaks[0].end);
+        assert_eq!(chrono::Duration::minutes(20), entry.get_break_duration());
+    }
 }
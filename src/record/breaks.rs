@@ -0,0 +1,224 @@
+use std::fmt::{Display, Write};
+
+use chrono::{DateTime, Duration, FixedOffset, TimeZone};
+
+use crate::error::{self, Result};
+
+const ANNOTATION: &str = "@break";
+
+/// A pause within a session, which isn't counted as work
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct Break<Tz: TimeZone> {
+    pub start: DateTime<Tz>,
+    /// Only the last break of the current session may still be open
+    pub end: Option<DateTime<Tz>>,
+    pub comment: Option<String>,
+}
+
+impl<Tz: TimeZone> Break<Tz> {
+    pub fn with_timezone<Tz2: TimeZone>(self, tz: &Tz2) -> Break<Tz2> {
+        Break {
+            start: self.start.with_timezone(tz),
+            end: self.end.map(|end| end.with_timezone(tz)),
+            comment: self.comment,
+        }
+    }
+
+    /// Moves the break by `delta`, keeping its length
+    pub fn shifted(self, delta: Duration) -> Self {
+        Self {
+            start: self.start + delta,
+            end: self.end.map(|end| end + delta),
+            comment: self.comment,
+        }
+    }
+
+    /// The end of the break, or `now` if it's still open
+    pub fn end_or(&self, now: &DateTime<Tz>) -> DateTime<Tz> {
+        self.end.clone().unwrap_or_else(|| now.clone())
+    }
+
+    /// How long the break lasted, or has lasted by `now` if it's still open
+    pub fn duration(&self, now: &DateTime<Tz>) -> Duration {
+        self.end_or(now).signed_duration_since(self.start.clone())
+    }
+
+    /// The break as it stands if the session ends at `at`, or `None` if it hadn't started by then
+    pub fn ended_by(self, at: &DateTime<Tz>) -> Option<Self> {
+        if self.start >= *at {
+            return None;
+        }
+        let end = self.end_or(at).min(at.clone());
+        Some(Self {
+            end: Some(end),
+            ..self
+        })
+    }
+}
+
+pub fn is_annotation(line: &str) -> bool {
+    line.split_once(' ').map_or(line, |(key, _)| key) == ANNOTATION
+}
+
+/// Parses a `@break START [END] [comment]` line, where a break without an end is still open
+pub fn parse_annotation(line: &str) -> Result<Break<FixedOffset>> {
+    let mut tokens = line.split_whitespace().skip(1).peekable();
+    let start = tokens
+        .next()
+        .ok_or_else(|| error::Main::InvalidBreak("missing its start time".to_owned()))?;
+    let start = DateTime::parse_from_rfc3339(start)?;
+    let end = tokens
+        .peek()
+        .and_then(|end| DateTime::parse_from_rfc3339(end).ok());
+    if end.is_some() {
+        tokens.next();
+    }
+    let comment = tokens.collect::<Vec<_>>().join(" ");
+
+    Ok(Break {
+        start,
+        end,
+        comment: Some(comment).filter(|comment| !comment.is_empty()),
+    })
+}
+
+/// Writes a `@break` line for each of `breaks`, with their times in `offset`
+pub fn write_annotations<Tz: TimeZone>(
+    breaks: &[Break<Tz>],
+    offset: &FixedOffset,
+    buf: &mut String,
+) -> std::fmt::Result {
+    for Break {
+        start,
+        end,
+        comment,
+    } in breaks
+    {
+        write!(
+            buf,
+            "{ANNOTATION} {}",
+            start.with_timezone(offset).to_rfc3339()
+        )?;
+        if let Some(end) = end {
+            write!(buf, " {}", end.with_timezone(offset).to_rfc3339())?;
+        }
+        if let Some(comment) = comment {
+            write!(buf, " {comment}")?;
+        }
+        writeln!(buf)?;
+    }
+    Ok(())
+}
+
+/// Checks that `breaks` are in order, don't overlap, and lie within a session from `start` to `end`.
+///
+/// Only a session that hasn't ended, where `end` is `None`, may have an open break, and only as its last.
+pub fn validate<Tz: TimeZone>(
+    breaks: &[Break<Tz>],
+    start: &DateTime<Tz>,
+    end: Option<&DateTime<Tz>>,
+) -> Result<()>
+where
+    Tz::Offset: Display,
+{
+    let invalid = |message: String| Err(error::Main::InvalidBreak(message));
+
+    let mut previous_end = start.clone();
+    for (i, pause) in breaks.iter().enumerate() {
+        let break_start = pause.start.to_rfc3339();
+        if pause.start < previous_end {
+            return invalid(format!(
+                "{break_start} is before the session or the break before it"
+            ));
+        }
+        if end.is_some_and(|end| pause.start > *end) {
+            return invalid(format!("{break_start} is after the session ended"));
+        }
+
+        match &pause.end {
+            Some(break_end) if *break_end < pause.start => {
+                return invalid(format!(
+                    "{} is before the break started at {break_start}",
+                    break_end.to_rfc3339()
+                ));
+            }
+            Some(break_end) if end.is_some_and(|end| break_end > end) => {
+                return invalid(format!(
+                    "{} is after the session ended",
+                    break_end.to_rfc3339()
+                ));
+            }
+            Some(break_end) => previous_end = break_end.clone(),
+            None if end.is_some() || i + 1 != breaks.len() => {
+                return invalid(format!("the one at {break_start} never ended"));
+            }
+            None => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{DateTime, FixedOffset};
+    use pretty_assertions::assert_eq;
+
+    use super::{parse_annotation, validate, write_annotations, Break};
+
+    fn at(hour: u32) -> DateTime<FixedOffset> {
+        format!("2024-01-01T{hour:02}:00:00+13:00").parse().unwrap()
+    }
+
+    #[test]
+    fn annotations() {
+        let lines = "@break 2024-01-01T12:00:00+13:00 2024-01-01T12:30:00+13:00 Lunch
+@break 2024-01-01T15:00:00+13:00
+";
+        let breaks = lines
+            .lines()
+            .map(|line| parse_annotation(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(Some("Lunch"), breaks[0].comment.as_deref());
+        assert_eq!(None, breaks[1].end);
+
+        let mut buf = String::new();
+        write_annotations(
+            &breaks,
+            &FixedOffset::east_opt(13 * 3600).unwrap(),
+            &mut buf,
+        )
+        .unwrap();
+        assert_eq!(lines, buf);
+
+        assert!(parse_annotation("@break").is_err());
+        assert!(parse_annotation("@break lunch").is_err());
+    }
+
+    #[test]
+    fn validation() {
+        let closed = |start, end| Break {
+            start: at(start),
+            end: Some(at(end)),
+            comment: None,
+        };
+        let open = |start| Break {
+            start: at(start),
+            end: None,
+            comment: None,
+        };
+
+        assert!(validate(&[closed(10, 11), closed(12, 13)], &at(9), Some(&at(17))).is_ok());
+        assert!(validate(&[closed(10, 11), open(12)], &at(9), None).is_ok());
+        // Overlapping, out of order, or outside the session
+        assert!(validate(&[closed(10, 12), closed(11, 13)], &at(9), None).is_err());
+        assert!(validate(&[closed(12, 13), closed(10, 11)], &at(9), None).is_err());
+        assert!(validate(&[closed(8, 10)], &at(9), None).is_err());
+        assert!(validate(&[closed(16, 18)], &at(9), Some(&at(17))).is_err());
+        assert!(validate(&[closed(11, 10)], &at(9), None).is_err());
+        // Open breaks must be the last of an open session
+        assert!(validate(&[open(10)], &at(9), Some(&at(17))).is_err());
+        assert!(validate(&[open(10), closed(11, 12)], &at(9), None).is_err());
+    }
+}
@@ -1,17 +1,51 @@
+pub mod html;
+pub mod svg;
 pub mod time_range;
 
 use std::ops::RangeInclusive;
 
-use chrono::{DateTime, Duration, NaiveDate};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
 use context::Context;
 
-use super::Record;
+use super::{Entry, OffsetChange, Record};
 use crate::{
     app::context,
     error::Result,
-    time::{self, range::DateTimeRange, ContextTimeZone, NaiveDateOperations},
+    time::{self, range::DateTimeRange, rounding::Tally, ContextTimeZone, NaiveDateOperations},
 };
 
+/// One line summary of an entry in `timezone`, used when confirming changes
+pub fn describe_entry<Tz: ContextTimeZone>(entry: &Entry<Utc>, timezone: &Tz) -> Result<String> {
+    let check_in = entry.check_in.with_timezone(timezone);
+    let check_out = entry.get_check_out()?.with_timezone(timezone);
+    let check_out_format = if check_out.date_naive() == check_in.date_naive() {
+        "%R"
+    } else {
+        "%a %e %b %Y %R"
+    };
+
+    let mut summary = format!(
+        "{} to {} ({})",
+        check_in.format("%a %e %b %Y %R"),
+        check_out.format(check_out_format),
+        time::human_readable_duration(&entry.get_work_duration())?
+    );
+    if !entry.breaks.is_empty() {
+        summary.push_str(&format!(
+            " with {} of breaks",
+            time::human_readable_duration(&entry.get_break_duration())?
+        ));
+    }
+    for comment in [&entry.in_comment, &entry.out_comment]
+        .into_iter()
+        .flatten()
+    {
+        summary.push_str(&format!(" \"{comment}\""));
+    }
+
+    Ok(summary)
+}
+
 #[allow(clippy::cast_precision_loss)]
 fn tween_dates<Tz: ContextTimeZone>(range: RangeInclusive<DateTime<Tz>>, pos: DateTime<Tz>) -> f32 {
     assert!(range.contains(&pos), "Pos date provided outside range");
@@ -63,12 +97,119 @@ pub fn paint_datetime_ranges_line<Tz: ContextTimeZone>(
         .collect()
 }
 
-pub fn paint_day_range<Tz: ContextTimeZone>(
+/// Whether `worked` met the scheduled target for `day`, blank if there is none or the day isn't over yet
+fn target_marker<Tz: ContextTimeZone>(ctx: &Context<Tz>, day: NaiveDate, worked: Duration) -> char {
+    let target = ctx.config.schedule.target(day);
+    if target.is_zero() {
+        ' '
+    } else if worked >= target {
+        '✓'
+    } else if day < ctx.timezone.now().date_naive() {
+        '✗'
+    } else {
+        ' '
+    }
+}
+
+/// Every session in `record`, with the current one ended now
+fn sessions<Tz: ContextTimeZone>(ctx: &Context<Tz>, record: &Record<Tz>) -> Result<Vec<Entry<Tz>>> {
+    record
+        .clone()
+        .into_iter()
+        .map(|item| item.into_entry(|| ctx.timezone.now()))
+        .collect()
+}
+
+/// Where `range` starts within `span`, and how much of it it covers, as percentages
+#[allow(clippy::cast_precision_loss)]
+fn percent_of<Tz: TimeZone>(
+    span: &RangeInclusive<DateTime<Tz>>,
+    range: &DateTimeRange<Tz>,
+) -> (f64, f64) {
+    let span_start = span.start().clone();
+    let span_length = (span.end().clone() - span_start.clone()).num_milliseconds() as f64;
+    let start = (range.start().clone() - span_start).num_milliseconds() as f64;
+    let length = (range.end().clone() - range.start().clone()).num_milliseconds() as f64;
+    (start / span_length * 100.0, length / span_length * 100.0)
+}
+
+/// Hover text for a bar drawn over `range`, with the comments of the session or break it's part of
+fn describe_range<Tz: ContextTimeZone>(
+    sessions: &[Entry<Tz>],
+    range: &DateTimeRange<Tz>,
+    is_break: bool,
+    time_format: &str,
+) -> Result<String> {
+    let (start, end) = (*range.start(), *range.end());
+    let mut lines = vec![format!(
+        "{}{} - {} ({})",
+        if is_break { "Break " } else { "" },
+        start.format(time_format),
+        end.format(time_format),
+        time::human_readable_duration(&(end - start))?
+    )];
+
+    let session = sessions.iter().find(|session| {
+        session.check_in <= start
+            && session
+                .get_check_out()
+                .is_ok_and(|check_out| end <= check_out)
+    });
+    let Some(session) = session else {
+        return Ok(lines.join("\n"));
+    };
+
+    if is_break {
+        let check_out = session.get_check_out()?;
+        lines.extend(
+            session
+                .breaks
+                .iter()
+                .find(|pause| pause.start <= start && end <= pause.end_or(&check_out))
+                .and_then(|pause| pause.comment.clone()),
+        );
+    } else {
+        if let Some(project) = &session.labels.project {
+            lines.push(format!("Project: {project}"));
+        }
+        if let Some(comment) = &session.in_comment {
+            lines.push(format!("In: {comment}"));
+        }
+        if let Some(comment) = &session.out_comment {
+            lines.push(format!("Out: {comment}"));
+        }
+    }
+
+    Ok(lines.join("\n"))
+}
+
+/// A day as the calendar shows it
+pub struct CalendarDay<Tz: TimeZone> {
+    pub date: NaiveDate,
+    pub span: RangeInclusive<DateTime<Tz>>,
+    /// The time worked, cropped to the day
+    pub ranges: Vec<DateTimeRange<Tz>>,
+    /// The breaks taken, cropped to the day
+    pub breaks: Vec<DateTimeRange<Tz>>,
+    /// Whether the schedule's target was met, when there is a schedule
+    pub target_marker: Option<char>,
+    /// The day's total, and any offset changes
+    pub annotations: Vec<String>,
+}
+
+/// Everything the calendar shows, worked out once so every output gives the same numbers
+pub struct Calendar<Tz: TimeZone> {
+    /// The total, number of work days and average, a line each
+    pub summary: Vec<String>,
+    pub days: Vec<CalendarDay<Tz>>,
+}
+
+pub fn calendar<Tz: ContextTimeZone>(
     ctx: &Context<Tz>,
     record: &Record<Tz>,
     range: RangeInclusive<NaiveDate>,
-    width: usize,
-) -> Result<()> {
+    offset_changes: &[OffsetChange],
+) -> Result<Calendar<Tz>> {
     let range_start = *range.start();
     let range_end = *range.end();
     let total_datetime_ranges = record.clone().try_into_cropped_datetime_ranges(
@@ -82,50 +223,92 @@ pub fn paint_day_range<Tz: ContextTimeZone>(
         .collect::<std::collections::BTreeSet<_>>()
         .len();
 
-    let total_duration: chrono::Duration = total_datetime_ranges.into_iter().sum();
-    println!(
-        "Total time: {} hours, {} minutes",
-        total_duration.num_hours(),
-        total_duration.num_minutes() % 60
-    );
+    let total = ctx.config.rounding.tally(&total_datetime_ranges);
+    let mut summary = vec![format!(
+        "Total time: {}",
+        total.display(time::hours_and_minutes)?
+    )];
 
     match i32::try_from(days_covered) {
         Ok(days_covered) => {
-            println!("# of work days: {days_covered}");
-            let average_duration = total_duration / days_covered;
-            println!(
-                "Average work day time: {} hours, {} minutes",
-                average_duration.num_hours(),
-                average_duration.num_minutes() % 60
-            );
+            summary.push(format!("# of work days: {days_covered}"));
+            let average = Tally {
+                raw: total.raw / days_covered,
+                rounded: total.rounded / days_covered,
+            };
+            summary.push(format!(
+                "Average work day time: {}",
+                average.display(time::hours_and_minutes)?
+            ));
         }
         Err(err) => {
-            println!("# of work days: FAILED TO PARSE FROM usize: {err}");
-            println!("Average work day time: UNAVAILABLE");
+            summary.push(format!("# of work days: FAILED TO PARSE FROM usize: {err}"));
+            summary.push("Average work day time: UNAVAILABLE".to_owned());
         }
     }
 
-    for (i, day) in range_start
-        .iter_days()
-        .take_while(|d| d <= &range_end)
-        .enumerate()
-    {
-        let day_span = time::day_timespan(ctx, day)?;
-        let datetime_ranges = record.clone().try_into_cropped_datetime_ranges(
-            ctx,
-            *day_span.start(),
-            *day_span.end(),
-        )?;
-        let duration: Duration = datetime_ranges.clone().into_iter().sum();
+    let mut days = vec![];
+    for date in range_start.iter_days().take_while(|d| d <= &range_end) {
+        let span = time::day_timespan(ctx, date)?;
+        let ranges =
+            record
+                .clone()
+                .try_into_cropped_datetime_ranges(ctx, *span.start(), *span.end())?;
+        let breaks =
+            record
+                .clone()
+                .try_into_cropped_break_ranges(ctx, *span.start(), *span.end())?;
+        let day_total = ctx.config.rounding.tally(&ranges);
+        let mut annotations = vec![];
+        if !day_total.rounded.is_zero() {
+            annotations.push(day_total.display(time::human_readable_duration)?);
+        }
+        annotations.extend(
+            offset_changes
+                .iter()
+                .filter(|change| change.date == date)
+                .map(|change| format!("({change})")),
+        );
+        days.push(CalendarDay {
+            date,
+            span,
+            ranges,
+            breaks,
+            // Measured unrounded, as `balance` does
+            target_marker: ctx
+                .config
+                .schedule
+                .is_set()
+                .then(|| target_marker(ctx, date, day_total.raw)),
+            annotations,
+        });
+    }
+
+    Ok(Calendar { summary, days })
+}
+
+pub fn paint_day_range<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    record: &Record<Tz>,
+    range: RangeInclusive<NaiveDate>,
+    width: usize,
+    offset_changes: &[OffsetChange],
+) -> Result<()> {
+    let Calendar { summary, days } = calendar(ctx, record, range, offset_changes)?;
+    for line in summary {
+        println!("{line}");
+    }
+
+    for (i, day) in days.into_iter().enumerate() {
+        let target_marker = day
+            .target_marker
+            .map(|marker| format!(" {marker}"))
+            .unwrap_or_default();
         println!(
-            "{} {} {}",
-            day.format("%F"),
-            paint_datetime_ranges_line(datetime_ranges, day_span, width, i % 2 != 0),
-            if duration.is_zero() {
-                String::new()
-            } else {
-                time::human_readable_duration(&duration)?
-            }
+            "{}{target_marker} {} {}",
+            day.date.format("%F"),
+            paint_datetime_ranges_line(day.ranges, day.span, width, i % 2 != 0),
+            day.annotations.join(" ")
         );
     }
 
@@ -137,7 +320,11 @@ mod tests {
     use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
 
     use super::Record;
-    use crate::{app::Context, record::Entry, time::range::DateTimeRange};
+    use crate::{
+        app::{config::Config, Context},
+        record::Entry,
+        time::range::DateTimeRange,
+    };
 
     fn tz() -> FixedOffset {
         FixedOffset::east_opt(0).unwrap()
@@ -160,7 +347,11 @@ mod tests {
     }
 
     fn line_from_record(record: Record<FixedOffset>, width: usize) -> String {
-        let ctx = Context::init(FixedOffset::east_opt(0).unwrap(), Default::default()).unwrap();
+        let ctx = Context::init(
+            FixedOffset::east_opt(0).unwrap(),
+            Default::default(),
+            Config::default(),
+        );
         let today_start = datetime(0, 0);
         let today_end = today_start.checked_add_days(chrono::Days::new(1)).unwrap();
         let today_end = today_end
@@ -253,4 +444,33 @@ mod tests {
         );
         assert_eq!("▓▓▓▓▓▓▒░▒░▒░▒░▒░▒░▓▓▓▓▓▓", line);
     }
+
+    #[test]
+    fn target_marker_unrounded() {
+        let config: Config = "[rounding]
+rule = \"up:1h\"
+
+[schedule]
+monday = \"8h\"
+"
+        .parse()
+        .unwrap();
+        let ctx = Context::init(FixedOffset::east_opt(0).unwrap(), true, config);
+        let record = Record::try_from(
+            "2024-01-01T09:00:00+00:00
+2024-01-01T16:10:00+00:00
+",
+        )
+        .unwrap();
+        let day = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+
+        let calendar = super::calendar(&ctx, &record, day..=day, &[]).unwrap();
+
+        // 7h10m rounds up to the 8h target, but falls short of it as worked
+        assert_eq!(
+            vec!["8 hours, 0 minutes (7 hours, 10 minutes unrounded)"],
+            calendar.days[0].annotations
+        );
+        assert_eq!(Some('✗'), calendar.days[0].target_marker);
+    }
 }
@@ -0,0 +1,113 @@
+use std::{fmt::Write, ops::RangeInclusive};
+
+use chrono::NaiveDate;
+
+use super::{calendar, describe_range, percent_of, sessions, Calendar};
+use crate::{
+    app::context::Context,
+    error::Result,
+    record::{OffsetChange, Record},
+    string::escape_html,
+    time::ContextTimeZone,
+};
+
+const STYLE: &str = "body { font-family: sans-serif; }
+td, th { padding: 2px 8px; text-align: left; white-space: nowrap; }
+.day { position: relative; width: 48em; height: 1.2em; background: #eee; }
+.day span { position: absolute; top: 0; bottom: 0; }
+.work { background: #3465a4; }
+.break { background: #9fb9d9; }
+";
+
+/// The calendar as a standalone HTML page, with each day's sessions drawn to the minute
+pub fn calendar_page<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    record: &Record<Tz>,
+    range: RangeInclusive<NaiveDate>,
+    offset_changes: &[OffsetChange],
+) -> Result<String> {
+    let title = format!(
+        "Calendar for {} to {}",
+        range.start().format("%F"),
+        range.end().format("%F")
+    );
+    let sessions = sessions(ctx, record)?;
+    let Calendar { summary, days } = calendar(ctx, record, range, offset_changes)?;
+    let time_format = ctx.config.formats.day.as_str();
+
+    let mut buf = String::new();
+    writeln!(buf, "<!DOCTYPE html>")?;
+    writeln!(buf, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(buf, "<title>{}</title>", escape_html(&title))?;
+    write!(buf, "<style>\n{STYLE}</style>\n")?;
+    writeln!(buf, "</head>\n<body>")?;
+    writeln!(buf, "<h1>{}</h1>", escape_html(&title))?;
+    let summary = summary
+        .iter()
+        .map(|line| escape_html(line))
+        .collect::<Vec<_>>();
+    writeln!(buf, "<p>{}</p>", summary.join("<br>\n"))?;
+    writeln!(buf, "<table>")?;
+    for day in days {
+        write!(buf, "<tr><th>{}</th>", day.date.format("%F"))?;
+        if let Some(marker) = day.target_marker {
+            write!(buf, "<td>{marker}</td>")?;
+        }
+        writeln!(buf, "<td><div class=\"day\">")?;
+        let bars = day
+            .ranges
+            .iter()
+            .map(|range| (range, false))
+            .chain(day.breaks.iter().map(|range| (range, true)));
+        for (range, is_break) in bars {
+            let (left, width) = percent_of(&day.span, range);
+            writeln!(
+                buf,
+                "<span class=\"{}\" style=\"left: {left:.3}%; width: {width:.3}%\" title=\"{}\"></span>",
+                if is_break { "break" } else { "work" },
+                escape_html(&describe_range(&sessions, range, is_break, time_format)?)
+            )?;
+        }
+        writeln!(
+            buf,
+            "</div></td><td>{}</td></tr>",
+            escape_html(&day.annotations.join(" "))
+        )?;
+    }
+    writeln!(buf, "</table>")?;
+    writeln!(buf, "</body>\n</html>")?;
+
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{FixedOffset, NaiveDate};
+
+    use super::calendar_page;
+    use crate::{
+        app::{config::Config, context::Context},
+        record::Record,
+    };
+
+    #[test]
+    fn calendar() {
+        let ctx = Context::init(FixedOffset::east_opt(0).unwrap(), true, Config::default());
+        let record = Record::try_from(
+            "2023-07-14T09:07:00+00:00 <Planning>
+2023-07-14T17:00:00+00:00 Done & dusted
+@project acme
+@break 2023-07-14T12:00:00+00:00 2023-07-14T12:45:00+00:00 Lunch
+
+2023-07-15T22:30:00+00:00
+2023-07-16T01:15:00+00:00
+",
+        )
+        .unwrap();
+        let day = |day| NaiveDate::from_ymd_opt(2023, 7, day).unwrap();
+
+        let html = calendar_page(&ctx, &record, day(14)..=day(16), &[]).unwrap();
+        assert!(html.contains("title=\"09:07 - 12:00 (2 hours, 53 minutes)\nProject: acme\nIn: &lt;Planning&gt;\nOut: Done &amp; dusted\""));
+        insta::assert_snapshot!(html);
+    }
+}
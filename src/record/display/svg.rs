@@ -0,0 +1,149 @@
+use std::{fmt::Write, ops::RangeInclusive};
+
+use chrono::{DateTime, Duration};
+
+use super::{describe_range, percent_of, sessions};
+use crate::{
+    app::context::Context,
+    error::Result,
+    record::Record,
+    string::escape_html,
+    time::{range::DateTimeRange, ContextTimeZone},
+};
+
+const HOUR_HEIGHT: f64 = 40.0;
+const LINE_HEIGHT: f64 = 18.0;
+const LABEL_WIDTH: f64 = 60.0;
+const BAR_WIDTH: f64 = 300.0;
+const MARGIN: f64 = 10.0;
+
+/// A day as an SVG image, an hour to a row, with its sessions drawn to the minute.
+///
+/// `summary` is printed above it, as it is above the terminal output.
+#[allow(clippy::cast_precision_loss)]
+pub fn day_image<Tz: ContextTimeZone>(
+    ctx: &Context<Tz>,
+    record: &Record<Tz>,
+    span: RangeInclusive<DateTime<Tz>>,
+    summary: &[String],
+    ranges: &[DateTimeRange<Tz>],
+    breaks: &[DateTimeRange<Tz>],
+) -> Result<String> {
+    let sessions = sessions(ctx, record)?;
+    let time_format = ctx.config.formats.day.as_str();
+    let (start, end) = (*span.start(), *span.end());
+    let hours = (end - start).num_minutes() as f64 / 60.0;
+    let top = MARGIN + LINE_HEIGHT * summary.len() as f64 + MARGIN;
+    let day_height = hours * HOUR_HEIGHT;
+    let width = LABEL_WIDTH + BAR_WIDTH + MARGIN;
+    let height = top + day_height + MARGIN;
+
+    let mut buf = String::new();
+    writeln!(
+        buf,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width:.0}\" height=\"{height:.0}\" viewBox=\"0 0 {width:.0} {height:.0}\" font-family=\"sans-serif\" font-size=\"12\">"
+    )?;
+    writeln!(
+        buf,
+        "<title>{}</title>",
+        escape_html(&start.format("%F").to_string())
+    )?;
+    for (i, line) in summary.iter().enumerate() {
+        writeln!(
+            buf,
+            "<text x=\"0\" y=\"{:.2}\">{}</text>",
+            MARGIN + LINE_HEIGHT * (i + 1) as f64,
+            escape_html(line)
+        )?;
+    }
+
+    writeln!(
+        buf,
+        "<rect x=\"{LABEL_WIDTH:.2}\" y=\"{top:.2}\" width=\"{BAR_WIDTH:.2}\" height=\"{day_height:.2}\" fill=\"#eee\"/>"
+    )?;
+    // Days changing to or from DST still get a row for each hour they have
+    let mut hour = start;
+    while hour < end {
+        let y = top + (hour - start).num_minutes() as f64 / 60.0 * HOUR_HEIGHT;
+        writeln!(
+            buf,
+            "<line x1=\"{LABEL_WIDTH:.2}\" y1=\"{y:.2}\" x2=\"{:.2}\" y2=\"{y:.2}\" stroke=\"#ccc\"/>",
+            LABEL_WIDTH + BAR_WIDTH
+        )?;
+        writeln!(
+            buf,
+            "<text x=\"0\" y=\"{:.2}\">{}</text>",
+            y + 12.0,
+            escape_html(&hour.format(time_format).to_string())
+        )?;
+        hour += Duration::hours(1);
+    }
+
+    let bars = ranges
+        .iter()
+        .map(|range| (range, false))
+        .chain(breaks.iter().map(|range| (range, true)));
+    for (range, is_break) in bars {
+        let (y, length) = percent_of(&span, range);
+        writeln!(
+            buf,
+            "<rect x=\"{LABEL_WIDTH:.2}\" y=\"{:.2}\" width=\"{BAR_WIDTH:.2}\" height=\"{:.2}\" fill=\"{}\"><title>{}</title></rect>",
+            top + y / 100.0 * day_height,
+            length / 100.0 * day_height,
+            if is_break { "#9fb9d9" } else { "#3465a4" },
+            escape_html(&describe_range(&sessions, range, is_break, time_format)?)
+        )?;
+    }
+    writeln!(buf, "</svg>")?;
+
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{FixedOffset, TimeZone};
+
+    use super::day_image;
+    use crate::{
+        app::{config::Config, context::Context},
+        record::Record,
+    };
+
+    #[test]
+    fn day() {
+        let tz = FixedOffset::east_opt(0).unwrap();
+        let ctx = Context::init(tz, true, Config::default());
+        let record = Record::try_from(
+            "2023-07-14T09:07:00+00:00 <Planning>
+2023-07-14T17:00:00+00:00 Done & dusted
+@break 2023-07-14T12:00:00+00:00 2023-07-14T12:45:00+00:00 Lunch
+",
+        )
+        .unwrap()
+        .with_timezone(&tz);
+        let start = tz.with_ymd_and_hms(2023, 7, 14, 0, 0, 0).unwrap();
+        let end = tz.with_ymd_and_hms(2023, 7, 15, 0, 0, 0).unwrap();
+        let ranges = record
+            .clone()
+            .try_into_cropped_datetime_ranges(&ctx, start, end)
+            .unwrap();
+        let breaks = record
+            .clone()
+            .try_into_cropped_break_ranges(&ctx, start, end)
+            .unwrap();
+
+        let svg = day_image(
+            &ctx,
+            &record,
+            start..=end,
+            &["Total time: 7:08".to_owned()],
+            &ranges,
+            &breaks,
+        )
+        .unwrap();
+        // 9:07 is 9 hours and 7 minutes of 40px hours below the 38px summary
+        assert!(svg.contains("<rect x=\"60.00\" y=\"402.67\""));
+        assert!(svg.contains("<title>Break 12:00 - 12:45 (45 minutes)\nLunch</title>"));
+        insta::assert_snapshot!(svg);
+    }
+}
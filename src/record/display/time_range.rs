@@ -1,11 +1,11 @@
 use std::fmt::Write;
 use std::ops::RangeInclusive;
 
-use chrono::DateTime;
+use chrono::{DateTime, TimeZone};
 
 use crate::{
     error::Result,
-    record::{display::time_range::printable::Info, Record},
+    record::{display::time_range::printable::Info, Break, Record, Session},
     time::ContextTimeZone,
 };
 
@@ -24,6 +24,10 @@ mod printable {
         SessionStart(NaiveDateTime, Option<String>),
         SessionEnd(NaiveDateTime, Option<String>),
         SessionWhole(NaiveDateTime, NaiveDateTime, Option<String>, Option<String>),
+        BreakSpan,
+        BreakStart(NaiveDateTime, Option<String>),
+        BreakEnd(NaiveDateTime),
+        BreakWhole(NaiveDateTime, NaiveDateTime, Option<String>),
         Multi(u32),
     }
 
@@ -38,6 +42,14 @@ mod printable {
                         write!(buf, "{blip}")?;
                     }
                 }
+                Info::BreakSpan
+                | Info::BreakStart(_, _)
+                | Info::BreakEnd(_)
+                | Info::BreakWhole(_, _, _) => {
+                    for _ in 0..width {
+                        write!(buf, "▄")?;
+                    }
+                }
                 _ => {
                     for _ in 0..width {
                         write!(buf, "▓")?;
@@ -69,7 +81,7 @@ mod printable {
                 self.info.print(width, bg_toggle)?
             )?;
             match &self.info {
-                Info::Empty | Info::SessionSpan => (),
+                Info::Empty | Info::SessionSpan | Info::BreakSpan => (),
                 Info::SessionStart(dt, comment) => {
                     write!(buf, " In @ {}", dt.format(date_format))?;
                     if let Some(comment) = comment {
@@ -105,6 +117,26 @@ mod printable {
                         _ => (),
                     }
                 }
+                Info::BreakStart(dt, comment) => {
+                    write!(buf, " Break @ {}", dt.format(date_format))?;
+                    if let Some(comment) = comment {
+                        write!(buf, " {comment}")?;
+                    }
+                }
+                Info::BreakEnd(dt) => {
+                    write!(buf, " Resume @ {}", dt.format(date_format))?;
+                }
+                Info::BreakWhole(start_dt, end_dt, comment) => {
+                    write!(
+                        buf,
+                        " Break {} -> {}",
+                        start_dt.format(date_format),
+                        end_dt.format(date_format),
+                    )?;
+                    if let Some(comment) = comment {
+                        write!(buf, " {comment}")?;
+                    }
+                }
                 Info::Multi(count) => {
                     write!(buf, " [{count} transitions overlapping]")?;
                 }
@@ -179,6 +211,12 @@ pub fn time_range<Tz: ContextTimeZone>(
                     Info::SessionStart(_, _) | Info::SessionEnd(_, _) => Info::Multi(2),
                     Info::SessionWhole(_, _, _, _) => Info::Multi(3),
                     Info::Multi(count) => Info::Multi(count + 1),
+                    Info::BreakSpan
+                    | Info::BreakStart(_, _)
+                    | Info::BreakEnd(_)
+                    | Info::BreakWhole(_, _, _) => {
+                        unreachable!("Breaks are only marked once every session is")
+                    }
                 };
                 if !first_session_line_printed && !matches!(line.info, Info::Empty) {
                     first_session_line_printed = true;
@@ -210,11 +248,23 @@ pub fn time_range<Tz: ContextTimeZone>(
                     *count += 1;
                 }
                 (Info::Empty, _) => (),
+                (
+                    Info::BreakSpan
+                    | Info::BreakStart(_, _)
+                    | Info::BreakEnd(_)
+                    | Info::BreakWhole(_, _, _),
+                    _,
+                ) => unreachable!("Breaks are only marked once every session is"),
             }
         }
     }
 
-    if let Some((check_in, in_comment)) = &record.current_session {
+    if let Some(Session {
+        check_in,
+        in_comment,
+        ..
+    }) = &record.current_session
+    {
         let mut first_session_line_printed = false;
         for (i, line) in lines.iter_mut().enumerate() {
             let point_start = points[i];
@@ -229,6 +279,12 @@ pub fn time_range<Tz: ContextTimeZone>(
                     Info::SessionEnd(_, _) => Info::Multi(2),
                     Info::SessionWhole(_, _, _, _) => Info::Multi(3),
                     Info::Multi(count) => Info::Multi(count + 1),
+                    Info::BreakSpan
+                    | Info::BreakStart(_, _)
+                    | Info::BreakEnd(_)
+                    | Info::BreakWhole(_, _, _) => {
+                        unreachable!("Breaks are only marked once every session is")
+                    }
                     Info::SessionSpan | Info::SessionStart(_, _) => {
                         unreachable!("Current session should not encounter another session's tail.")
                     }
@@ -240,9 +296,63 @@ pub fn time_range<Tz: ContextTimeZone>(
         }
     }
 
+    let breaks = record
+        .get_entries()
+        .iter()
+        .flat_map(|entry| &entry.breaks)
+        .chain(
+            record
+                .current_session
+                .iter()
+                .flat_map(|session| &session.breaks),
+        );
+    for pause in breaks {
+        mark_break(&mut lines, &points, range_slice, pause, &now);
+    }
+
     Ok(TimeRange(lines))
 }
 
+/// Shows `pause` on the lines that would otherwise just show the session it's in
+fn mark_break<Tz: TimeZone>(
+    lines: &mut [Line],
+    points: &[DateTime<Tz>],
+    slice: chrono::Duration,
+    pause: &Break<Tz>,
+    now: &DateTime<Tz>,
+) {
+    let end = pause.end_or(now);
+    let spans = lines
+        .iter()
+        .zip(points)
+        .enumerate()
+        .filter(|(_, (line, point))| {
+            line.info == Info::SessionSpan
+                && pause.start < (*point).clone() + slice
+                && **point < end
+        })
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let start = pause.start.naive_local();
+    let end = pause.end.as_ref().map(DateTime::naive_local);
+
+    match (spans.as_slice(), end) {
+        ([], _) => {}
+        ([only], Some(end)) => {
+            lines[*only].info = Info::BreakWhole(start, end, pause.comment.clone());
+        }
+        ([first, rest @ ..], end) => {
+            lines[*first].info = Info::BreakStart(start, pause.comment.clone());
+            for i in rest {
+                lines[*i].info = Info::BreakSpan;
+            }
+            if let (Some(last), Some(end)) = (rest.last(), end) {
+                lines[*last].info = Info::BreakEnd(end);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
@@ -543,4 +653,53 @@ mod test {
         ];
         assert_eq!(expected, tr.0);
     }
+
+    #[test]
+    fn time_range_breaks() {
+        let record_file = "2023-11-02T08:30:00+00:00
+2023-11-02T17:00:00+00:00 Done for the day
+@break 2023-11-02T12:10:00+00:00 2023-11-02T13:40:00+00:00 Lunch
+@break 2023-11-02T15:00:00+00:00 2023-11-02T15:15:00+00:00
+
+2023-11-02T20:00:00+00:00
+@break 2023-11-02T21:30:00+00:00 Dinner
+";
+        let record = Record::try_from(record_file).unwrap();
+        let tr = super::time_range(
+            &record,
+            dt!(2023, 11, 3),
+            dt!(2023, 11, 2)..=dt!(2023, 11, 3),
+            24,
+        )
+        .unwrap();
+        insta::assert_display_snapshot!(tr.print(6, "%R").unwrap());
+    }
+
+    #[test]
+    fn time_range_repeated_hour() {
+        use chrono_tz::America::Havana;
+
+        use crate::{
+            app::{config::Config, context::Context},
+            time::NaiveDateOperations,
+        };
+
+        let record_file = "2023-11-05T03:30:00.000000+00:00
+2023-11-05T06:30:00.000000+00:00 Across the change back
+";
+        let record = Record::try_from(record_file)
+            .unwrap()
+            .with_timezone(&Havana);
+        let ctx = Context::init(Havana, false, Config::default());
+        let day = NaiveDate::from_ymd_opt(2023, 11, 5).unwrap();
+        let tr = super::time_range(
+            &record,
+            dt!(2024, 1, 1).with_timezone(&Havana),
+            day.into_day_start(&ctx).unwrap()
+                ..=day.succ_opt().unwrap().into_day_start(&ctx).unwrap(),
+            25,
+        )
+        .unwrap();
+        insta::assert_display_snapshot!(tr.print(6, "%R").unwrap());
+    }
 }
@@ -0,0 +1,88 @@
+use std::{collections::BTreeSet, fmt::Write};
+
+use crate::error::{self, Result};
+
+#[derive(Clone, Default, PartialEq, Eq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct Labels {
+    pub project: Option<String>,
+    pub tags: BTreeSet<String>,
+}
+
+impl Labels {
+    pub fn try_new(project: Option<String>, tags: Vec<String>) -> Result<Self> {
+        let project = project
+            .map(|project| project.trim().to_owned())
+            .filter(|project| !project.is_empty());
+        if let Some(project) = &project {
+            if project.contains('\n') {
+                return Err(error::Main::InvalidProject(project.clone()));
+            }
+        }
+
+        let tags = tags
+            .into_iter()
+            .map(|tag| {
+                if tag.is_empty() || tag.contains(char::is_whitespace) {
+                    Err(error::Main::InvalidTag(tag))
+                } else {
+                    Ok(tag)
+                }
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { project, tags })
+    }
+
+    /// Parses an `@`-prefixed annotation line from the record into these labels
+    pub fn parse_annotation(&mut self, line: &str) -> Result<()> {
+        let (key, value) = line
+            .trim_start_matches('@')
+            .split_once(' ')
+            .map_or((line.trim_start_matches('@'), ""), |(key, value)| {
+                (key, value.trim())
+            });
+
+        match key {
+            "project" if !value.is_empty() => self.project = Some(value.to_owned()),
+            "tags" => self
+                .tags
+                .extend(value.split_whitespace().map(ToOwned::to_owned)),
+            _ => return Err(error::Main::UnknownAnnotation(line.to_owned())),
+        }
+
+        Ok(())
+    }
+
+    pub fn write_annotations(&self, buf: &mut String) -> std::fmt::Result {
+        if let Some(project) = &self.project {
+            writeln!(buf, "@project {project}")?;
+        }
+        if !self.tags.is_empty() {
+            let tags = self.tags.iter().map(String::as_str).collect::<Vec<_>>();
+            writeln!(buf, "@tags {}", tags.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn is_annotation(line: &str) -> bool {
+    line.starts_with('@')
+}
+
+#[derive(Default)]
+pub struct Filter {
+    pub project: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl Filter {
+    pub fn matches(&self, labels: &Labels) -> bool {
+        let project_matches = self
+            .project
+            .as_ref()
+            .is_none_or(|project| labels.project.as_ref() == Some(project));
+
+        project_matches && self.tags.iter().all(|tag| labels.tags.contains(tag))
+    }
+}
@@ -0,0 +1,293 @@
+use chrono::{DateTime, FixedOffset};
+
+use super::{breaks, labels, Entry, Labels, Record, Session};
+use crate::error::{self, Result};
+
+/// A run of non-blank lines in the record, each alongside its line number
+pub struct Paragraph<'a> {
+    lines: Vec<(usize, &'a str)>,
+}
+
+pub enum Parsed {
+    Entry(Entry<FixedOffset>),
+    CurrentSession(Session<FixedOffset>),
+}
+
+pub fn paragraphs(source: &str) -> Vec<Paragraph<'_>> {
+    let mut paragraphs = vec![];
+    let mut lines = vec![];
+
+    for (i, text) in source.lines().enumerate() {
+        let text = text.trim();
+        // Comments are skipped without ending the paragraph they're in
+        if text.starts_with('#') {
+            continue;
+        }
+        if text.is_empty() {
+            if !lines.is_empty() {
+                paragraphs.push(Paragraph {
+                    lines: std::mem::take(&mut lines),
+                });
+            }
+        } else {
+            lines.push((i + 1, text));
+        }
+    }
+
+    if !lines.is_empty() {
+        paragraphs.push(Paragraph { lines });
+    }
+
+    paragraphs
+}
+
+/// The source of the last paragraph, found without looking at the lines before it
+pub fn last_paragraph(source: &str) -> &str {
+    let mut end = source.len();
+    let mut in_paragraph = false;
+    for (newline, _) in source.rmatch_indices('\n') {
+        let text = source[newline + 1..end].trim();
+        if text.is_empty() && in_paragraph {
+            return &source[end + 1..];
+        }
+        in_paragraph |= !text.is_empty() && !text.starts_with('#');
+        end = newline;
+    }
+    source
+}
+
+fn at_line(line: usize, text: &str, err: error::Main) -> error::Main {
+    error::Main::RecordLine {
+        line,
+        text: text.to_owned(),
+        source: Box::new(err),
+    }
+}
+
+fn parse_timestamp_line(
+    &(line, text): &(usize, &str),
+) -> Result<(DateTime<FixedOffset>, Option<String>)> {
+    let mut tokens = text.split(' ').map(str::trim).filter(|t| !t.is_empty());
+    let timestamp = tokens.next().expect("paragraph lines are never blank");
+    let comment = tokens.collect::<Vec<_>>().join(" ");
+
+    let timestamp =
+        DateTime::parse_from_rfc3339(timestamp).map_err(|err| at_line(line, text, err.into()))?;
+
+    Ok((timestamp, Some(comment).filter(|c| !c.is_empty())))
+}
+
+impl Paragraph<'_> {
+    /// The line number of the first line in this paragraph
+    pub fn line(&self) -> usize {
+        self.lines[0].0
+    }
+
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(|(_, text)| *text)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Only the last paragraph of a record may be a current session
+    pub fn parse(&self, is_last: bool) -> Result<Parsed> {
+        let (annotations, lines): (Vec<_>, Vec<_>) = self
+            .lines
+            .iter()
+            .partition(|(_, text)| labels::is_annotation(text));
+
+        let mut labels = Labels::default();
+        let mut breaks = vec![];
+        for &&(line, text) in &annotations {
+            if breaks::is_annotation(text) {
+                breaks
+                    .push(breaks::parse_annotation(text).map_err(|err| at_line(line, text, err))?);
+            } else {
+                labels
+                    .parse_annotation(text)
+                    .map_err(|err| at_line(line, text, err))?;
+            }
+        }
+        let invalid_breaks = |err| at_line(self.line(), &self.text(), err);
+
+        match lines.as_slice() {
+            [check_in_line, check_out_line] => {
+                let (check_in, in_comment) = parse_timestamp_line(check_in_line)?;
+                let (check_out, out_comment) = parse_timestamp_line(check_out_line)?;
+                let entry = Entry::try_new(check_in, check_out, in_comment, out_comment)
+                    .map_err(|err| at_line(check_out_line.0, check_out_line.1, err))?;
+                Ok(Parsed::Entry(
+                    entry
+                        .with_labels(labels)
+                        .with_breaks(breaks)
+                        .map_err(invalid_breaks)?,
+                ))
+            }
+            [session_line] if is_last => {
+                let (check_in, in_comment) = parse_timestamp_line(session_line)?;
+                breaks::validate(&breaks, &check_in, None).map_err(invalid_breaks)?;
+                Ok(Parsed::CurrentSession(Session {
+                    check_in,
+                    in_comment,
+                    labels,
+                    recorded_offset: *check_in.offset(),
+                    breaks,
+                }))
+            }
+            _ => Err(at_line(
+                self.line(),
+                &self.text(),
+                error::Main::EntryIncorrectNumberOfLines,
+            )),
+        }
+    }
+}
+
+impl TryFrom<&str> for Record<FixedOffset> {
+    type Error = error::Main;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let paragraphs = paragraphs(value);
+        let last = paragraphs.len().saturating_sub(1);
+
+        let mut entries = vec![];
+        let mut current_session = None;
+
+        for (i, paragraph) in paragraphs.iter().enumerate() {
+            match paragraph.parse(i == last)? {
+                Parsed::Entry(entry) => entries.push(entry),
+                Parsed::CurrentSession(session) => current_session = Some(session),
+            }
+        }
+
+        Ok(Self {
+            entries,
+            current_session,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::last_paragraph;
+    use crate::{error, record::Record};
+
+    fn error_line(record: &str) -> (usize, String) {
+        let Err(error::Main::RecordLine { line, text, .. }) = Record::try_from(record) else {
+            panic!("expected a record line error");
+        };
+        (line, text)
+    }
+
+    #[test]
+    fn bad_timestamp_line() {
+        assert_eq!(
+            (5, "2023-01-01T0300:00+00:00 Oops".to_owned()),
+            error_line(
+                "2023-01-01T00:00:00+00:00
+2023-01-01T01:00:00+00:00
+
+2023-01-01T02:00:00+00:00
+2023-01-01T0300:00+00:00 Oops
+"
+            )
+        );
+    }
+
+    #[test]
+    fn incorrect_number_of_lines() {
+        assert_eq!(
+            (
+                2,
+                "2023-01-01T00:00:00+00:00\n2023-01-01T01:00:00+00:00\n2023-01-01T02:00:00+00:00"
+                    .to_owned()
+            ),
+            error_line(
+                "
+2023-01-01T00:00:00+00:00
+2023-01-01T01:00:00+00:00
+2023-01-01T02:00:00+00:00
+
+2023-01-01T04:00:00+00:00
+"
+            )
+        );
+    }
+
+    #[test]
+    fn unknown_annotation() {
+        assert_eq!(
+            (3, "@projcet acme".to_owned()),
+            error_line(
+                "2023-01-01T00:00:00+00:00
+2023-01-01T01:00:00+00:00
+@projcet acme
+"
+            )
+        );
+    }
+
+    #[test]
+    fn invalid_break() {
+        assert_eq!(
+            (
+                1,
+                "2023-01-01T00:00:00+00:00\n2023-01-01T01:00:00+00:00\n@break 2023-01-01T00:30:00+00:00"
+                    .to_owned()
+            ),
+            error_line(
+                "2023-01-01T00:00:00+00:00
+2023-01-01T01:00:00+00:00
+@break 2023-01-01T00:30:00+00:00
+"
+            )
+        );
+        assert_eq!(
+            (2, "@break lunch".to_owned()),
+            error_line(
+                "2023-01-01T00:00:00+00:00
+@break lunch
+"
+            )
+        );
+    }
+
+    #[test]
+    fn comments_keep_line_numbers() {
+        assert_eq!(
+            (4, "2023-01-01T0100:00+00:00".to_owned()),
+            error_line(
+                "# A comment
+2023-01-01T00:00:00+00:00
+# Inside an entry
+2023-01-01T0100:00+00:00
+"
+            )
+        );
+    }
+
+    #[test]
+    fn last_paragraph_only() {
+        let last = "2023-01-01T02:00:00+00:00 Started
+# Comments don't end a paragraph
+2023-01-01T03:00:00+00:00
+@project acme
+
+";
+        let source = format!(
+            "2023-01-01T00:00:00+00:00
+2023-01-01T01:00:00+00:00
+
+{last}"
+        );
+        assert_eq!(last, last_paragraph(&source));
+        assert_eq!(
+            "2023-01-01T00:00:00+00:00",
+            last_paragraph("2023-01-01T00:00:00+00:00")
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use std::{
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDateTime, SubsecRound, Utc};
+
+use super::{parse, Record};
+use crate::error::{self, Result};
+
+const DIR: &str = ".punch_clock";
+const BACKUP_ID_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+/// Reads and writes the record file, making sure writes never clobber changes made by another process
+pub struct Storage {
+    dir: PathBuf,
+    /// Hash of the record file as it was when loaded
+    fingerprint: u64,
+    /// How many snapshots of the record to keep in `backups/`
+    keep_backups: usize,
+}
+
+fn fingerprint(source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn record_path(dir: &Path) -> PathBuf {
+    dir.join("record")
+}
+
+fn parse_record(source: &str) -> Result<Record<Utc>> {
+    let record: Record<FixedOffset> = source.try_into()?;
+    Ok(record.with_timezone(&Utc))
+}
+
+/// Advisory lock, held while checking and replacing the record.
+///
+/// The record itself can't be locked because it is replaced on every write.
+fn lock(dir: &Path) -> Result<fs::File> {
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(dir.join("record.lock"))?;
+    file.lock()?;
+    Ok(file)
+}
+
+pub fn init() -> Result<()> {
+    if Path::new(DIR).exists() {
+        return Err(error::Main::AlreadyInitialized);
+    }
+
+    fs::create_dir(DIR)?;
+    fs::write(record_path(Path::new(DIR)), "")?;
+
+    Ok(())
+}
+
+impl Storage {
+    /// Loads the record of the project in the current directory, returning `None` if it isn't initialized
+    pub fn load(keep_backups: usize) -> Result<Option<(Self, Record<Utc>)>> {
+        let dir = Path::new(DIR);
+        if !dir.exists() {
+            return Ok(None);
+        }
+
+        Self::load_from(dir, keep_backups).map(Some)
+    }
+
+    /// Only the last session in the record, without parsing the rest of it
+    pub fn load_latest() -> Result<Option<Record<Utc>>> {
+        let path = record_path(Path::new(DIR));
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let source = fs::read_to_string(path)?;
+        parse_record(parse::last_paragraph(&source)).map(Some)
+    }
+
+    fn load_from(dir: &Path, keep_backups: usize) -> Result<(Self, Record<Utc>)> {
+        let source = fs::read_to_string(record_path(dir))?;
+        let record = parse_record(&source)?;
+
+        let storage = Self {
+            dir: dir.to_path_buf(),
+            fingerprint: fingerprint(&source),
+            keep_backups,
+        };
+
+        Ok((storage, record))
+    }
+
+    fn backups_dir(&self) -> PathBuf {
+        self.dir.join("backups")
+    }
+
+    /// Ids of the saved snapshots, oldest first
+    pub fn backups(&self) -> Result<Vec<String>> {
+        let mut ids = match fs::read_dir(self.backups_dir()) {
+            Ok(entries) => entries
+                .map(|entry| {
+                    entry?
+                        .file_name()
+                        .into_string()
+                        .map_err(error::Main::OsStringParseFail)
+                })
+                .collect::<Result<Vec<_>>>()?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(err) => return Err(err.into()),
+        };
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// When the snapshot with `id` was taken
+    pub fn backup_time(id: &str) -> Option<DateTime<Utc>> {
+        NaiveDateTime::parse_from_str(id, BACKUP_ID_FORMAT)
+            .ok()
+            .map(|time| time.and_utc())
+    }
+
+    /// An id for a snapshot taken now, after every existing one even if they were taken in the same millisecond
+    fn next_backup_id(&self) -> Result<String> {
+        // Ids only go down to milliseconds
+        let now = Utc::now().trunc_subsecs(3);
+        let time = match self.backups()?.last().and_then(|id| Self::backup_time(id)) {
+            Some(newest) if newest >= now => newest + Duration::milliseconds(1),
+            _ => now,
+        };
+        Ok(time.format(BACKUP_ID_FORMAT).to_string())
+    }
+
+    pub fn load_backup(&self, id: &str) -> Result<Record<Utc>> {
+        if !self.backups()?.iter().any(|backup| backup == id) {
+            return Err(error::Main::NoSuchBackup(id.to_owned()));
+        }
+
+        parse_record(&fs::read_to_string(self.backups_dir().join(id))?)
+    }
+
+    /// Copies the record as it is on disk into `backups/`, then removes the oldest snapshots past `keep_backups`
+    fn back_up(&self, source: &str) -> Result<()> {
+        if self.keep_backups == 0 {
+            return Ok(());
+        }
+
+        fs::create_dir_all(self.backups_dir())?;
+        let id = self.next_backup_id()?;
+        fs::write(self.backups_dir().join(id), source)?;
+
+        let backups = self.backups()?;
+        let excess = backups.len().saturating_sub(self.keep_backups);
+        for id in &backups[..excess] {
+            fs::remove_file(self.backups_dir().join(id))?;
+        }
+
+        Ok(())
+    }
+
+    /// Atomically replaces the record file with `record`.
+    ///
+    /// Fails with [`error::Main::RecordChangedOnDisk`] if the file no longer matches what was loaded.
+    pub fn save(&mut self, record: &Record<Utc>) -> Result<()> {
+        let path = record_path(&self.dir);
+        let _lock = lock(&self.dir)?;
+
+        let previous = fs::read_to_string(&path)?;
+        if fingerprint(&previous) != self.fingerprint {
+            return Err(error::Main::RecordChangedOnDisk);
+        }
+
+        self.back_up(&previous)?;
+
+        let source = record.serialize()?;
+        let temp_path = self.dir.join("record.tmp");
+        let mut temp = fs::File::create(&temp_path)?;
+        temp.write_all(source.as_bytes())?;
+        temp.sync_all()?;
+        drop(temp);
+
+        fs::rename(&temp_path, &path)?;
+        // Make sure the rename itself survives a crash
+        #[cfg(unix)]
+        fs::File::open(&self.dir)?.sync_all()?;
+
+        self.fingerprint = fingerprint(&source);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use chrono::Utc;
+
+    use super::Storage;
+    use crate::{error, record::Labels};
+
+    fn temp_project(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("record"),
+            "2023-01-01T00:00:00+00:00\n2023-01-01T01:00:00+00:00\n",
+        )
+        .unwrap();
+        dir
+    }
+
+    #[test]
+    fn save_replaces_record() {
+        let dir = temp_project("punch_clock_storage_save");
+        let (mut storage, mut record) = Storage::load_from(&dir, 0).unwrap();
+
+        record
+            .clock_in(None, Labels::default(), Utc::now().fixed_offset())
+            .unwrap();
+        storage.save(&record).unwrap();
+        record.clock_out(None, Utc::now().fixed_offset()).unwrap();
+        storage.save(&record).unwrap();
+
+        let (_, saved) = Storage::load_from(&dir, 0).unwrap();
+        assert_eq!(2, saved.get_entries().len());
+        assert!(!dir.join("record.tmp").exists());
+        assert!(!dir.join("backups").exists());
+    }
+
+    #[test]
+    fn backups_rotate() {
+        let dir = temp_project("punch_clock_storage_backups");
+        let (mut storage, mut record) = Storage::load_from(&dir, 2).unwrap();
+
+        for clock_in in [true, false, true] {
+            if clock_in {
+                record
+                    .clock_in(None, Labels::default(), Utc::now().fixed_offset())
+                    .unwrap();
+            } else {
+                record.clock_out(None, Utc::now().fixed_offset()).unwrap();
+            }
+            // Saves in quick succession, likely within the same millisecond, still get their own snapshots
+            storage.save(&record).unwrap();
+        }
+
+        let backups = storage.backups().unwrap();
+        assert_eq!(2, backups.len());
+        // The oldest snapshot was rotated out, and the newest is the record from before the last save
+        let oldest = storage.load_backup(&backups[0]).unwrap();
+        assert!(oldest.get_current_session().is_some());
+        let latest = storage.load_backup(&backups[1]).unwrap();
+        assert_eq!(2, latest.get_entries().len());
+        assert!(latest.get_current_session().is_none());
+    }
+
+    #[test]
+    fn save_refuses_to_clobber_changes() {
+        let dir = temp_project("punch_clock_storage_clobber");
+        let (mut storage, mut record) = Storage::load_from(&dir, 0).unwrap();
+
+        fs::write(dir.join("record"), "").unwrap();
+
+        record
+            .clock_in(None, Labels::default(), Utc::now().fixed_offset())
+            .unwrap();
+        let err = storage.save(&record).unwrap_err();
+        assert!(matches!(err, error::Main::RecordChangedOnDisk));
+        assert_eq!("", fs::read_to_string(dir.join("record")).unwrap());
+    }
+}
@@ -1,30 +1,259 @@
-use std::{path::PathBuf, process::Command};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::Instant,
+};
+
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::config::Hooks,
+    record::{Entry, Labels, Session},
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("'{0}' hook exited with code {1}")]
+    Failed(String, i32),
+    #[error("'{0}' hook was killed after running for {1}s")]
+    TimedOut(String, u64),
 }
 
-fn run_in_current_dir(name: &str) -> Result<(), Error> {
-    let hook_file_name = format!("./{name}");
-    let hook_file_path = PathBuf::from(&hook_file_name);
-    if !hook_file_path.exists() {
-        return Ok(());
+/// What happens when a hook fails or times out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Policy {
+    /// Stop `punch_clock` with an error.
+    ///
+    /// A failing `before-` hook stops the punch, and the rest of its scripts, before the record is saved.
+    /// Other hooks run once the record is saved, so every one of their scripts, and the later hooks for the same punch, still run before the first failure is reported.
+    #[default]
+    Abort,
+    /// Print a warning to stderr and carry on
+    Warn,
+    /// Carry on silently
+    Ignore,
+}
+
+/// What a hook is told about the punch that triggered it
+#[derive(Debug, Clone, Serialize)]
+pub struct Payload {
+    /// When the punch is recorded, or the time the hook is about, in RFC 3339
+    pub timestamp: String,
+    pub comment: Option<String>,
+    pub project: Option<String>,
+    pub tags: Vec<String>,
+    /// When the session being punched out of, or added, began, in RFC 3339
+    pub session_start: Option<String>,
+    /// The time worked in the session, not counting breaks
+    pub session_duration_seconds: Option<i64>,
+}
+
+impl Payload {
+    pub fn clock_in(at: DateTime<FixedOffset>, comment: Option<String>, labels: &Labels) -> Self {
+        Self {
+            timestamp: at.to_rfc3339(),
+            comment,
+            project: labels.project.clone(),
+            tags: labels.tags.iter().cloned().collect(),
+            session_start: None,
+            session_duration_seconds: None,
+        }
+    }
+
+    /// Just a time, and maybe the comment that went with it
+    pub fn at(at: DateTime<FixedOffset>, comment: Option<String>) -> Self {
+        Self {
+            timestamp: at.to_rfc3339(),
+            comment,
+            project: None,
+            tags: vec![],
+            session_start: None,
+            session_duration_seconds: None,
+        }
+    }
+
+    /// A complete session, described as though it was just punched out of
+    pub fn entry(entry: &Entry<Utc>) -> crate::error::Result<Self> {
+        Ok(Self {
+            timestamp: entry.recorded_check_out()?.to_rfc3339(),
+            comment: entry.out_comment.clone(),
+            project: entry.labels.project.clone(),
+            tags: entry.labels.tags.iter().cloned().collect(),
+            session_start: Some(entry.recorded_check_in().to_rfc3339()),
+            session_duration_seconds: Some(
+                (entry.get_work_duration() - entry.get_break_duration()).num_seconds(),
+            ),
+        })
+    }
+
+    /// `session` is `None` when not clocked in, in which case the punch itself will fail
+    pub fn clock_out(
+        at: DateTime<FixedOffset>,
+        comment: Option<String>,
+        session: Option<&Session<Utc>>,
+    ) -> Self {
+        Self {
+            timestamp: at.to_rfc3339(),
+            comment,
+            project: session.and_then(|session| session.labels.project.clone()),
+            tags: session
+                .map(|session| session.labels.tags.iter().cloned().collect())
+                .unwrap_or_default(),
+            session_start: session.map(|session| session.recorded_check_in().to_rfc3339()),
+            session_duration_seconds: session.map(|session| {
+                let at = at.with_timezone(&Utc);
+                (at - session.check_in - session.get_break_duration(&at)).num_seconds()
+            }),
+        }
+    }
+
+    /// The `PUNCH_CLOCK_*` variables set for the hook, empty where there is nothing to say
+    fn variables(&self, event: &str, root: &Path) -> Vec<(&'static str, String)> {
+        vec![
+            ("PUNCH_CLOCK_EVENT", event.to_owned()),
+            ("PUNCH_CLOCK_TIMESTAMP", self.timestamp.clone()),
+            (
+                "PUNCH_CLOCK_COMMENT",
+                self.comment.clone().unwrap_or_default(),
+            ),
+            (
+                "PUNCH_CLOCK_PROJECT",
+                self.project.clone().unwrap_or_default(),
+            ),
+            ("PUNCH_CLOCK_TAGS", self.tags.join(" ")),
+            (
+                "PUNCH_CLOCK_SESSION_START",
+                self.session_start.clone().unwrap_or_default(),
+            ),
+            (
+                "PUNCH_CLOCK_SESSION_DURATION",
+                self.session_duration_seconds
+                    .map(|seconds| seconds.to_string())
+                    .unwrap_or_default(),
+            ),
+            ("PUNCH_CLOCK_ROOT", root.display().to_string()),
+        ]
+    }
+}
+
+/// The JSON written to the hook's stdin
+#[derive(Serialize)]
+struct Message<'a> {
+    event: &'a str,
+    root: &'a Path,
+    #[serde(flatten)]
+    payload: &'a Payload,
+}
+
+/// The hook called `name`, then any scripts in `name.d`, in order of their file names
+fn scripts(name: &str) -> Result<Vec<PathBuf>, Error> {
+    let mut scripts = vec![];
+
+    let single = PathBuf::from(format!("./{name}"));
+    if single.is_file() {
+        scripts.push(single);
+    }
+
+    let dir = PathBuf::from(format!("./{name}.d"));
+    if dir.is_dir() {
+        let mut in_dir = std::fs::read_dir(dir)?
+            .map(|file| file.map(|file| file.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Skip hidden files and editor backups
+        in_dir.retain(|path| {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            path.is_file() && !file_name.starts_with('.') && !file_name.ends_with('~')
+        });
+        in_dir.sort();
+        scripts.extend(in_dir);
+    }
+
+    Ok(scripts)
+}
+
+fn run_script(
+    script: &Path,
+    name: &str,
+    root: &Path,
+    payload: &Payload,
+    timeout: Duration,
+) -> Result<(), Error> {
+    let mut child = Command::new(script)
+        .envs(payload.variables(name, root))
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        let message = Message {
+            event: name,
+            root,
+            payload,
+        };
+        match stdin.write_all(&serde_json::to_vec(&message)?) {
+            // Hooks that don't read their stdin may have exited already
+            Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => {}
+            result => result?,
+        }
     }
 
-    let status = Command::new(hook_file_name).status()?;
+    let display_name = script
+        .strip_prefix(".")
+        .unwrap_or(script)
+        .display()
+        .to_string();
+    let started = Instant::now();
+    let timeout = timeout.to_std().unwrap_or_default();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if started.elapsed() >= timeout {
+            child.kill()?;
+            child.wait()?;
+            return Err(Error::TimedOut(display_name, timeout.as_secs()));
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
 
     if !status.success() {
-        let exit_code = status.code().unwrap_or(-1);
-        println!("'{name}' hook exited with code {exit_code}");
-        std::process::exit(exit_code);
+        return Err(Error::Failed(display_name, status.code().unwrap_or(-1)));
     }
 
     Ok(())
 }
 
-pub fn run(name: &str) -> Result<(), Error> {
+fn run_in_current_dir(
+    name: &str,
+    root: &Path,
+    payload: &Payload,
+    settings: &Hooks,
+) -> Result<(), Error> {
+    let (policy, timeout) = settings.settings(name);
+    let runs_before_save = name.starts_with("before-");
+
+    let mut first_error = None;
+    for script in scripts(name)? {
+        match (run_script(&script, name, root, payload, timeout), policy) {
+            (Ok(()), _) | (Err(_), Policy::Ignore) => {}
+            (Err(err), Policy::Warn) => eprintln!("Warning: {err}"),
+            (Err(err), Policy::Abort) if runs_before_save => return Err(err),
+            (Err(err), Policy::Abort) => {
+                first_error.get_or_insert(err);
+            }
+        }
+    }
+
+    first_error.map_or(Ok(()), Err)
+}
+
+pub fn run(name: &str, payload: &Payload, settings: &Hooks) -> Result<(), Error> {
     let origin_dir = std::env::current_dir()?;
 
     let hooks_dir = PathBuf::from(".punch_clock/hooks");
@@ -34,9 +263,62 @@ pub fn run(name: &str) -> Result<(), Error> {
 
     std::env::set_current_dir(hooks_dir)?;
 
-    let result = run_in_current_dir(name);
+    let result = run_in_current_dir(name, &origin_dir, payload, settings);
 
     std::env::set_current_dir(origin_dir)?;
 
     result
 }
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use chrono::{DateTime, Utc};
+    use pretty_assertions::assert_eq;
+
+    use super::{Message, Payload};
+    use crate::record::{Labels, Session};
+
+    #[test]
+    fn clock_out_payload() {
+        let at = DateTime::parse_from_rfc3339("2024-01-01T10:30:00+13:00").unwrap();
+        let session = Session {
+            check_in: "2023-12-31T20:00:00Z".parse::<DateTime<Utc>>().unwrap(),
+            in_comment: None,
+            labels: Labels::try_new(Some("acme".to_owned()), vec!["review".to_owned()]).unwrap(),
+            recorded_offset: *at.offset(),
+            breaks: vec![],
+        };
+        let payload = Payload::clock_out(at, Some("Done".to_owned()), Some(&session));
+
+        assert_eq!(
+            vec![
+                ("PUNCH_CLOCK_EVENT", "out".to_owned()),
+                (
+                    "PUNCH_CLOCK_TIMESTAMP",
+                    "2024-01-01T10:30:00+13:00".to_owned()
+                ),
+                ("PUNCH_CLOCK_COMMENT", "Done".to_owned()),
+                ("PUNCH_CLOCK_PROJECT", "acme".to_owned()),
+                ("PUNCH_CLOCK_TAGS", "review".to_owned()),
+                (
+                    "PUNCH_CLOCK_SESSION_START",
+                    "2024-01-01T09:00:00+13:00".to_owned()
+                ),
+                ("PUNCH_CLOCK_SESSION_DURATION", "5400".to_owned()),
+                ("PUNCH_CLOCK_ROOT", "/work".to_owned()),
+            ],
+            payload.variables("out", Path::new("/work"))
+        );
+        assert_eq!(
+            r#"{"event":"out","root":"/work","timestamp":"2024-01-01T10:30:00+13:00","comment":"Done","project":"acme","tags":["review"],"session_start":"2024-01-01T09:00:00+13:00","session_duration_seconds":5400}"#,
+            serde_json::to_string(&Message {
+                event: "out",
+                root: Path::new("/work"),
+                payload: &payload,
+            })
+            .unwrap()
+        );
+    }
+}
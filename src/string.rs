@@ -1,5 +1,21 @@
 use crate::error::{Main, Result};
 
+/// Escapes text to be put between HTML tags or in a quoted attribute
+pub fn escape_html(str: &str) -> String {
+    let mut escaped = String::with_capacity(str.len());
+    for c in str.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 pub fn assert_no_newlines(str: String) -> Result<String> {
     if str.contains('\n') {
         Err(Main::CommentWithNewlines)
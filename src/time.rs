@@ -1,11 +1,12 @@
 pub mod range;
+pub mod rounding;
 
 use std::{
     fmt::{self, Display, Write},
     ops::RangeInclusive,
 };
 
-use chrono::{DateTime, Days, Duration, TimeZone};
+use chrono::{DateTime, Days, Duration, FixedOffset, TimeZone};
 
 use crate::{
     app::context::Context,
@@ -35,6 +36,139 @@ pub fn human_readable_duration(duration: &Duration) -> Result<String, fmt::Error
     Ok(buf)
 }
 
+/// Like [`human_readable_duration`], but never in days
+pub fn hours_and_minutes(duration: &Duration) -> Result<String, fmt::Error> {
+    Ok(format!(
+        "{} hours, {} minutes",
+        duration.num_hours(),
+        duration.num_minutes() % 60
+    ))
+}
+
+/// Like `2h13m`, or `13m` under an hour
+pub fn compact_duration(duration: &Duration) -> String {
+    match duration.num_hours() {
+        0 => format!("{}m", duration.num_minutes()),
+        hours => format!("{hours}h{:02}m", duration.num_minutes() % 60),
+    }
+}
+
+/// [`human_readable_duration`] with a leading `+` or `-`
+pub fn signed_human_readable_duration(duration: &Duration) -> Result<String, fmt::Error> {
+    let sign = if *duration < Duration::zero() {
+        '-'
+    } else {
+        '+'
+    };
+    Ok(format!("{sign}{}", human_readable_duration(duration)?))
+}
+
+/// Parses durations like `90m`, `1h30m` or `2d`
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+
+    let mut total = Duration::zero();
+    let mut rest = value;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        if digits == 0 {
+            return None;
+        }
+        let amount = rest[..digits].parse().ok()?;
+        let unit_len = rest[digits..]
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len() - digits);
+        let unit = match &rest[digits..digits + unit_len] {
+            "d" => Duration::try_days(amount)?,
+            "h" => Duration::try_hours(amount)?,
+            "m" => Duration::try_minutes(amount)?,
+            "s" => Duration::try_seconds(amount)?,
+            _ => return None,
+        };
+        total = total.checked_add(&unit)?;
+        rest = &rest[digits + unit_len..];
+    }
+
+    Some(total)
+}
+
+pub fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = <String as serde::Deserialize>::deserialize(deserializer)?;
+    parse_duration(&value).ok_or_else(|| {
+        serde::de::Error::custom(format!(
+            "invalid duration {value:?}, expected something like \"90m\" or \"1h30m\""
+        ))
+    })
+}
+
+pub fn deserialize_optional_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserialize_duration(deserializer).map(Some)
+}
+
+/// Parses UTC offsets like `+05:30`, `-3` or `13`
+pub fn parse_offset(value: &str) -> Option<FixedOffset> {
+    let value = value.trim();
+    let (sign, rest) = match value.as_bytes().first()? {
+        b'+' => (1, &value[1..]),
+        b'-' => (-1, &value[1..]),
+        _ => (1, value),
+    };
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    // `parse` would take another sign, like the one in "+-5"
+    let number = |text: &str| {
+        if text.is_empty() || !text.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        text.parse::<i32>().ok()
+    };
+    let hours = number(hours)?;
+    let minutes = number(minutes)?;
+    if !(0..60).contains(&minutes) {
+        return None;
+    }
+
+    let seconds = hours.checked_mul(3600)?.checked_add(minutes * 60)?;
+    FixedOffset::east_opt(sign * seconds)
+}
+
+/// Accepts an offset either in whole hours, as it used to be, or as a string for [`parse_offset`]
+pub fn deserialize_offset<'de, D>(deserializer: D) -> Result<Option<FixedOffset>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Offset {
+        Hours(i32),
+        Text(String),
+    }
+
+    let offset = match <Offset as serde::Deserialize>::deserialize(deserializer)? {
+        Offset::Hours(hours) => hours.checked_mul(3600).and_then(FixedOffset::east_opt),
+        Offset::Text(text) => parse_offset(&text),
+    };
+    offset.map(Some).ok_or_else(|| {
+        serde::de::Error::custom("invalid UTC offset, expected something like 13 or \"+05:30\"")
+    })
+}
+
+pub fn deserialize_timezone<'de, D>(deserializer: D) -> Result<Option<chrono_tz::Tz>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = <String as serde::Deserialize>::deserialize(deserializer)?;
+    value.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
 #[derive(Debug)]
 pub struct Ago(pub chrono::Duration);
 
@@ -49,6 +183,14 @@ impl Display for Ago {
     }
 }
 
+/// `datetime` in `timezone`, as punches and sessions are shown in messages
+pub fn local_display<Tz: TimeZone, Tz2: ContextTimeZone>(
+    datetime: &DateTime<Tz>,
+    timezone: &Tz2,
+) -> String {
+    datetime.with_timezone(timezone).format("%c").to_string()
+}
+
 pub fn day_timespan<Tz: ContextTimeZone>(
     ctx: &Context<Tz>,
     day: chrono::NaiveDate,
@@ -86,24 +228,102 @@ impl ContextTimeZone for chrono::FixedOffset {
     }
 }
 
+impl ContextTimeZone for chrono_tz::Tz {
+    type Offset = <Self as TimeZone>::Offset;
+
+    fn now(&self) -> DateTime<Self> {
+        chrono::Utc::now().with_timezone(self)
+    }
+}
+
 impl NaiveDateOperations for chrono::NaiveDate {
+    /// The first instant of the day. Some timezones skip or repeat midnight when changing to or from DST, so this is the
+    /// end of the skipped hour, or the first of the repeated midnights.
     fn into_day_start<Tz: ContextTimeZone>(self, ctx: &Context<Tz>) -> Result<DateTime<Tz>> {
-        match self
-            .and_time(chrono::NaiveTime::MIN)
-            .and_local_timezone(ctx.timezone)
-        {
-            chrono::LocalResult::Single(dt) => Ok(dt),
-            chrono::LocalResult::None | chrono::LocalResult::Ambiguous(_, _) => {
-                todo!("I don't know how to handle this atm")
-            }
-        }
+        let midnight = self.and_time(chrono::NaiveTime::MIN);
+        // No timezone has ever skipped more than a day at once
+        (0..=24 * 60)
+            .map(|minutes| midnight + Duration::minutes(minutes))
+            .find_map(|local| local.and_local_timezone(ctx.timezone).earliest())
+            .ok_or(error::Main::DateOutOfRange)
     }
 
+    /// The last instant of the day, which isn't always 24 hours after its start
     fn into_day_end<Tz: ContextTimeZone>(self, ctx: &Context<Tz>) -> Result<DateTime<Tz>> {
-        self.into_day_start(ctx)?
-            .checked_add_days(Days::new(1))
+        self.checked_add_days(Days::new(1))
             .ok_or(error::Main::DateOutOfRange)?
+            .into_day_start(ctx)?
             .checked_sub_signed(Duration::nanoseconds(1))
             .ok_or(error::Main::DateOutOfRange)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, NaiveDate};
+    use chrono_tz::{America::Havana, Europe::London};
+    use pretty_assertions::assert_eq;
+
+    use super::{day_timespan, parse_offset, NaiveDateOperations};
+    use crate::app::{config::Config, context::Context};
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn day_length(ctx: &Context<chrono_tz::Tz>, day: NaiveDate) -> Duration {
+        let span = day_timespan(ctx, day).unwrap();
+        *span.end() - *span.start() + Duration::nanoseconds(1)
+    }
+
+    #[test]
+    fn midnight_skipped() {
+        // Cuba moved its clocks from midnight to 1am on 2023-03-12
+        let ctx = Context::init(Havana, false, Config::default());
+
+        let start = date(2023, 3, 12).into_day_start(&ctx).unwrap();
+        assert_eq!("2023-03-12T01:00:00-04:00", start.to_rfc3339());
+        assert_eq!(
+            start - Duration::nanoseconds(1),
+            date(2023, 3, 11).into_day_end(&ctx).unwrap()
+        );
+        assert_eq!(Duration::hours(23), day_length(&ctx, date(2023, 3, 12)));
+    }
+
+    #[test]
+    fn midnight_repeated() {
+        // Cuba moved its clocks from 1am back to midnight on 2023-11-05
+        let ctx = Context::init(Havana, false, Config::default());
+
+        let start = date(2023, 11, 5).into_day_start(&ctx).unwrap();
+        assert_eq!("2023-11-05T00:00:00-04:00", start.to_rfc3339());
+        assert_eq!(Duration::hours(25), day_length(&ctx, date(2023, 11, 5)));
+        assert_eq!(Duration::hours(24), day_length(&ctx, date(2023, 11, 4)));
+    }
+
+    #[test]
+    fn transitions_during_the_day() {
+        let ctx = Context::init(London, false, Config::default());
+
+        assert_eq!(Duration::hours(23), day_length(&ctx, date(2023, 3, 26)));
+        assert_eq!(Duration::hours(25), day_length(&ctx, date(2023, 10, 29)));
+    }
+
+    #[test]
+    fn offsets() {
+        let seconds = |value| parse_offset(value).map(|offset| offset.local_minus_utc());
+
+        assert_eq!(Some(19_800), seconds("+05:30"));
+        assert_eq!(Some(-12_600), seconds("-03:30"));
+        assert_eq!(Some(46_800), seconds("13"));
+        assert_eq!(Some(-10_800), seconds("-3"));
+        assert_eq!(None, seconds("+05:60"));
+        assert_eq!(None, seconds("Pacific/Auckland"));
+        assert_eq!(None, seconds("+25"));
+        assert_eq!(None, seconds("9999999"));
+        assert_eq!(None, seconds("-2147483647"));
+        assert_eq!(None, seconds("+-5"));
+        assert_eq!(None, seconds("--5"));
+        assert_eq!(None, seconds("5:+30"));
+    }
+}
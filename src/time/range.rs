@@ -10,7 +10,11 @@ use crate::{
 
 #[derive(Clone)]
 #[allow(clippy::module_name_repetitions)]
-pub struct DateTimeRange<Tz: TimeZone>(RangeInclusive<DateTime<Tz>>);
+pub struct DateTimeRange<Tz: TimeZone> {
+    range: RangeInclusive<DateTime<Tz>>,
+    /// When the session this was worked in started, which differs from the range's start if breaks split it
+    session_start: DateTime<Tz>,
+}
 
 impl<Tz: TimeZone> Span<Duration> for DateTimeRange<Tz>
 where
@@ -19,7 +23,7 @@ where
     type Output = Duration;
 
     fn span(&self) -> Self::Output {
-        *self.0.end() - *self.0.start()
+        *self.range.end() - *self.range.start()
     }
 }
 
@@ -34,21 +38,41 @@ where
                 end: end.to_rfc3339(),
             });
         }
-        Ok(Self(start..=end))
+        Ok(Self {
+            session_start: start.clone(),
+            range: start..=end,
+        })
+    }
+
+    /// The same range, moved to start at `start` and end at `end`, still in the same session
+    pub fn cropped(self, start: DateTime<Tz>, end: DateTime<Tz>) -> Result<Self> {
+        Ok(Self::new(start, end)?.in_session(self.session_start))
     }
 }
 
 impl<Tz: TimeZone> DateTimeRange<Tz> {
     pub fn into_bounds(self) -> (DateTime<Tz>, DateTime<Tz>) {
-        self.0.into_inner()
+        self.range.into_inner()
     }
 
     pub fn start(&self) -> &DateTime<Tz> {
-        self.0.start()
+        self.range.start()
     }
 
     pub fn end(&self) -> &DateTime<Tz> {
-        self.0.end()
+        self.range.end()
+    }
+
+    /// The same range, as part of the session started at `session_start`
+    pub fn in_session(self, session_start: DateTime<Tz>) -> Self {
+        Self {
+            session_start,
+            ..self
+        }
+    }
+
+    pub fn session_start(&self) -> &DateTime<Tz> {
+        &self.session_start
     }
 
     pub fn days_covered(&self) -> Vec<NaiveDate> {
@@ -65,7 +89,10 @@ impl<Tz: TimeZone> From<Entry<Tz>> for DateTimeRange<Tz> {
     fn from(entry: Entry<Tz>) -> Self {
         let check_out = entry.check_in.clone() + entry.get_work_duration();
 
-        Self(entry.check_in..=check_out)
+        Self {
+            session_start: entry.check_in.clone(),
+            range: entry.check_in..=check_out,
+        }
     }
 }
 
@@ -0,0 +1,291 @@
+use std::{collections::BTreeMap, fmt, fmt::Display, str::FromStr};
+
+use chrono::{DateTime, Duration, NaiveDate, TimeZone};
+use serde::Deserialize;
+
+use super::{parse_duration, range::DateTimeRange};
+use crate::range::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Nearest,
+    Up,
+    Down,
+}
+
+/// Rounds durations to a multiple of `increment`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rounding {
+    pub direction: Direction,
+    pub increment: Duration,
+}
+
+impl Rounding {
+    pub fn apply(&self, duration: Duration) -> Duration {
+        let increment = self.increment.num_milliseconds();
+        let millis = duration.num_milliseconds();
+        let rounded_down = millis - millis.rem_euclid(increment);
+        let remainder = millis - rounded_down;
+
+        let rounded = match self.direction {
+            Direction::Down => rounded_down,
+            Direction::Up if remainder > 0 => rounded_down + increment,
+            Direction::Up => rounded_down,
+            // Halfway rounds up
+            Direction::Nearest if remainder * 2 >= increment => rounded_down + increment,
+            Direction::Nearest => rounded_down,
+        };
+
+        Duration::milliseconds(rounded)
+    }
+}
+
+/// Parses rules like `nearest:15m`, `up:6m` or `down:1h`
+impl FromStr for Rounding {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!("invalid rounding {value:?}, expected something like \"nearest:15m\", \"up:6m\" or \"down:1h\"")
+        };
+
+        let (direction, increment) = value.split_once(':').ok_or_else(invalid)?;
+        let direction = match direction {
+            "nearest" => Direction::Nearest,
+            "up" => Direction::Up,
+            "down" => Direction::Down,
+            _ => return Err(invalid()),
+        };
+        let increment = parse_duration(increment)
+            .filter(|increment| increment.num_milliseconds() > 0)
+            .ok_or_else(invalid)?;
+
+        Ok(Self {
+            direction,
+            increment,
+        })
+    }
+}
+
+/// Reads after "Rounded", like "to the nearest 15m"
+impl Display for Rounding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let minutes = self.increment.num_minutes();
+        let increment = match (minutes / 60, minutes % 60) {
+            (0, _) if self.increment.num_seconds() % 60 != 0 => {
+                format!("{}s", self.increment.num_seconds())
+            }
+            (0, minutes) => format!("{minutes}m"),
+            (hours, 0) => format!("{hours}h"),
+            (hours, minutes) => format!("{hours}h{minutes}m"),
+        };
+        match self.direction {
+            Direction::Nearest => write!(f, "to the nearest {increment}"),
+            Direction::Up => write!(f, "up to the next {increment}"),
+            Direction::Down => write!(f, "down to the last {increment}"),
+        }
+    }
+}
+
+/// Whether a [`Policy`] rounds each session on its own, or each day's total
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scope {
+    #[default]
+    Session,
+    Day,
+}
+
+/// How totals are rounded wherever they are printed
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    /// Like "nearest:15m", "up:6m" or "down:1h"
+    pub rule: Option<Rounding>,
+    pub per: Scope,
+    /// Any session or day with time in it counts for at least this long
+    pub minimum: Duration,
+}
+
+impl Policy {
+    fn round(&self, duration: Duration) -> Duration {
+        if duration <= Duration::zero() {
+            return duration;
+        }
+
+        self.rule
+            .map_or(duration, |rule| rule.apply(duration))
+            .max(self.minimum)
+    }
+
+    /// Sum of `datetime_ranges`, grouped by the session they were worked in or the day they start on, and rounded
+    pub fn total<Tz: TimeZone>(&self, datetime_ranges: &[DateTimeRange<Tz>]) -> Duration
+    where
+        Tz::Offset: Copy,
+    {
+        match self.per {
+            // The pieces of a session split by breaks are rounded together, as `invoice` does
+            Scope::Session => {
+                let mut sessions = BTreeMap::<DateTime<Tz>, Duration>::new();
+                for datetime_range in datetime_ranges {
+                    *sessions
+                        .entry(*datetime_range.session_start())
+                        .or_insert_with(Duration::zero) += datetime_range.span();
+                }
+                sessions
+                    .into_values()
+                    .map(|session| self.round(session))
+                    .sum()
+            }
+            Scope::Day => {
+                let mut days = BTreeMap::<NaiveDate, Duration>::new();
+                for datetime_range in datetime_ranges {
+                    *days
+                        .entry(datetime_range.start().date_naive())
+                        .or_insert_with(Duration::zero) += datetime_range.span();
+                }
+                days.into_values().map(|day| self.round(day)).sum()
+            }
+        }
+    }
+
+    /// Tallies a single session, which is rounded the same whatever the scope
+    pub fn session(&self, duration: Duration) -> Tally {
+        Tally {
+            raw: duration,
+            rounded: self.round(duration),
+        }
+    }
+
+    pub fn tally<Tz: TimeZone>(&self, datetime_ranges: &[DateTimeRange<Tz>]) -> Tally
+    where
+        Tz::Offset: Copy,
+    {
+        Tally {
+            raw: datetime_ranges.iter().map(Span::span).sum(),
+            rounded: self.total(datetime_ranges),
+        }
+    }
+}
+
+/// A total as worked, and as rounded by a [`Policy`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tally {
+    pub raw: Duration,
+    pub rounded: Duration,
+}
+
+impl Tally {
+    /// The rounded total, followed by the raw one if they differ
+    pub fn display(
+        &self,
+        format: impl Fn(&Duration) -> Result<String, fmt::Error>,
+    ) -> Result<String, fmt::Error> {
+        let rounded = format(&self.rounded)?;
+        if self.raw == self.rounded {
+            Ok(rounded)
+        } else {
+            Ok(format!("{rounded} ({} unrounded)", format(&self.raw)?))
+        }
+    }
+}
+
+pub fn deserialize_rounding<'de, D>(deserializer: D) -> Result<Option<Rounding>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = <String as serde::Deserialize>::deserialize(deserializer)?;
+    value.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, TimeZone};
+    use pretty_assertions::assert_eq;
+
+    use super::{Policy, Rounding, Scope, Tally};
+    use crate::time::range::DateTimeRange;
+
+    fn round(rule: &str, minutes: i64, seconds: i64) -> i64 {
+        let rounding: Rounding = rule.parse().unwrap();
+        rounding
+            .apply(Duration::minutes(minutes) + Duration::seconds(seconds))
+            .num_minutes()
+    }
+
+    #[test]
+    fn directions() {
+        assert_eq!(15, round("nearest:15m", 7, 30));
+        assert_eq!(0, round("nearest:15m", 7, 29));
+        assert_eq!(6, round("up:6m", 0, 1));
+        assert_eq!(6, round("up:6m", 6, 0));
+        assert_eq!(60, round("down:1h", 119, 59));
+    }
+
+    #[test]
+    fn policy_scopes() {
+        let policy = |per| Policy {
+            rule: Some("up:15m".parse().unwrap()),
+            per,
+            minimum: Duration::minutes(30),
+        };
+        let range = |day, hour, minutes| {
+            let start = chrono::Utc
+                .with_ymd_and_hms(2024, 1, day, hour, 0, 0)
+                .unwrap();
+            DateTimeRange::new(start, start + Duration::minutes(minutes)).unwrap()
+        };
+        let ranges = [range(1, 9, 10), range(1, 13, 50), range(2, 9, 1)];
+
+        // 10m is lifted to the minimum, 50m rounds up to 1h
+        assert_eq!(
+            Duration::minutes(90),
+            policy(Scope::Session).total(&ranges[..2])
+        );
+        // 1h exactly stays put
+        assert_eq!(Duration::hours(1), policy(Scope::Day).total(&ranges[..2]));
+        assert_eq!(
+            Tally {
+                raw: Duration::hours(1),
+                rounded: Duration::minutes(90),
+            },
+            policy(Scope::Session).tally(&ranges[..2])
+        );
+        assert_eq!(
+            "1h30m (1h0m unrounded)",
+            policy(Scope::Session)
+                .tally(&ranges[..2])
+                .display(|d| Ok(format!("{}h{}m", d.num_hours(), d.num_minutes() % 60)))
+                .unwrap()
+        );
+        assert_eq!(Duration::minutes(1), Policy::default().total(&ranges[2..]));
+    }
+
+    #[test]
+    fn sessions_with_breaks() {
+        let policy = Policy {
+            rule: Some("up:15m".parse().unwrap()),
+            per: Scope::Session,
+            minimum: Duration::hours(1),
+        };
+        let record = crate::record::Record::try_from(
+            "2024-01-01T09:00:00+00:00
+2024-01-01T17:00:00+00:00
+@break 2024-01-01T12:00:00+00:00 2024-01-01T12:10:00+00:00
+@break 2024-01-01T15:00:00+00:00 2024-01-01T15:10:00+00:00
+",
+        )
+        .unwrap();
+        let ranges = record.get_entries()[0].clone().into_work_ranges().unwrap();
+        assert_eq!(3, ranges.len());
+
+        // 7h40m is rounded once, not each of 3h, 2h50m and 1h50m
+        assert_eq!(Duration::minutes(7 * 60 + 45), policy.total(&ranges));
+    }
+
+    #[test]
+    fn invalid_rules() {
+        assert!("nearest".parse::<Rounding>().is_err());
+        assert!("sideways:15m".parse::<Rounding>().is_err());
+        assert!("up:0m".parse::<Rounding>().is_err());
+    }
+}